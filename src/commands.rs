@@ -0,0 +1,262 @@
+use std::{any::Any, fmt};
+
+use eyre::{Report, Result};
+
+use crate::{
+    custom_errors::CustomErrors,
+    entities::{entity::Entity, Entities},
+    system::{
+        access::Access,
+        system_param::{SystemParam, SystemView},
+    },
+    World,
+};
+
+type CommandFn = Box<dyn FnOnce(&mut World) -> Result<()> + Send + Sync>;
+
+struct Command {
+    kind: &'static str,
+    entity: Entity,
+    apply: CommandFn,
+}
+
+/// Structural changes waiting for `&mut World`. Fill it through `Commands` while queries are
+/// still borrowing the world, then `apply` it once they're gone.
+#[derive(Default)]
+pub struct CommandQueue {
+    commands: Vec<Command>,
+}
+
+impl CommandQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Applies every queued command in the order it was recorded. A failing command doesn't stop
+    /// the ones after it, its error is returned instead.
+    pub fn apply(&mut self, world: &mut World) -> Vec<CommandError> {
+        self.commands
+            .drain(..)
+            .enumerate()
+            .filter_map(|(index, command)| {
+                (command.apply)(world).err().map(|error| CommandError {
+                    index,
+                    kind: command.kind,
+                    entity: command.entity,
+                    error,
+                })
+            })
+            .collect()
+    }
+
+    fn push(
+        &mut self,
+        kind: &'static str,
+        entity: Entity,
+        apply: impl FnOnce(&mut World) -> Result<()> + Send + Sync + 'static,
+    ) {
+        self.commands.push(Command {
+            kind,
+            entity,
+            apply: Box::new(apply),
+        });
+    }
+}
+
+impl fmt::Debug for CommandQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.commands
+                    .iter()
+                    .map(|command| (command.kind, command.entity)),
+            )
+            .finish()
+    }
+}
+
+/// Folds the errors of an applied queue into a single `CommandsFailed`.
+pub(crate) fn into_result(errors: Vec<CommandError>) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    Err(CustomErrors::CommandsFailed(errors.join("; ")).into())
+}
+
+#[derive(Debug)]
+pub struct CommandError {
+    /// Position of the command in the queue it was applied from.
+    pub index: usize,
+    pub kind: &'static str,
+    pub entity: Entity,
+    pub error: Report,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "command {} ({} {:?}) failed: {}",
+            self.index, self.kind, self.entity, self.error
+        )
+    }
+}
+
+/// Records spawns, despawns, inserts and removals into a `CommandQueue`. Only needs `&World`, so
+/// it can be used inside a query loop.
+pub struct Commands<'w, 's> {
+    queue: &'s mut CommandQueue,
+    entities: &'w Entities,
+}
+
+impl<'w, 's> Commands<'w, 's> {
+    pub fn new(queue: &'s mut CommandQueue, world: &'w World) -> Self {
+        Self::for_entities(queue, &world.entities)
+    }
+
+    pub(crate) fn for_entities(queue: &'s mut CommandQueue, entities: &'w Entities) -> Self {
+        Self { queue, entities }
+    }
+
+    /// Reserves an entity right away, so later commands can already refer to it. It comes alive,
+    /// without components, when the queue is applied.
+    pub fn spawn(&mut self) -> Entity {
+        let entity = self.entities.reserve_entity();
+        self.queue.push("spawn", entity, move |world: &mut World| {
+            world.entities.spawn_reserved(entity)?;
+            Ok(())
+        });
+        entity
+    }
+
+    pub fn despawn(&mut self, entity: Entity) -> &mut Self {
+        self.queue
+            .push("despawn", entity, move |world: &mut World| {
+                world.delete_entity_by_id(entity)
+            });
+        self
+    }
+
+    pub fn insert(&mut self, entity: Entity, data: impl Any + Send + Sync) -> &mut Self {
+        self.queue.push("insert", entity, move |world: &mut World| {
+            world.add_component_to_entity_by_id(data, entity)
+        });
+        self
+    }
+
+    /// Queues any other work on the world. `entity` is only what a failure gets reported against.
+    pub fn add(
+        &mut self,
+        entity: Entity,
+        command: impl FnOnce(&mut World) -> Result<()> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.queue.push("custom", entity, command);
+        self
+    }
+
+    pub fn remove<T: Any>(&mut self, entity: Entity) -> &mut Self {
+        self.queue.push("remove", entity, move |world: &mut World| {
+            world.delete_component_by_entity_id::<T>(entity)
+        });
+        self
+    }
+}
+
+/// The queue lives in the system's state and is applied as soon as the system is done.
+impl SystemParam for Commands<'_, '_> {
+    type State = CommandQueue;
+    type Item<'w, 's> = Commands<'w, 's>;
+
+    fn access(_access: &mut Access) -> Result<()> {
+        Ok(())
+    }
+
+    fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        view: &mut SystemView<'w>,
+    ) -> Result<Self::Item<'w, 's>> {
+        Ok(Commands {
+            queue: state,
+            entities: view.entities(),
+        })
+    }
+
+    fn apply(state: &mut Self::State, world: &mut World) -> Result<()> {
+        into_result(state.apply(world))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Health(u32);
+    struct Dead;
+
+    #[test]
+    fn commands_are_recorded_during_a_query() -> Result<()> {
+        let mut world = World::new();
+        world.register_component::<Health>();
+        world.register_component::<Dead>();
+
+        let alive = world.create_entity().with_component(Health(3))?.entity();
+        let dying = world.create_entity().with_component(Health(0))?.entity();
+
+        let mut queue = CommandQueue::new();
+        let mut commands = Commands::new(&mut queue, &world);
+        let mut spawned = vec![];
+        for (entity, health) in world.query::<&Health>()?.iter() {
+            if health.0 == 0 {
+                commands.insert(entity, Dead).despawn(entity);
+                let replacement = commands.spawn();
+                commands.insert(replacement, Health(10));
+                spawned.push(replacement);
+            }
+        }
+
+        assert!(queue.apply(&mut world).is_empty());
+        assert!(queue.is_empty());
+        assert!(world.is_alive(alive));
+        assert!(!world.is_alive(dying));
+
+        let healths: Vec<_> = world
+            .query::<&Health>()?
+            .iter()
+            .map(|(entity, health)| (entity, health.0))
+            .collect();
+        assert_eq!(healths, vec![(alive, 3), (spawned[0], 10)]);
+        Ok(())
+    }
+
+    #[test]
+    fn errors_are_reported_per_command() -> Result<()> {
+        let mut world = World::new();
+        world.register_component::<Health>();
+        let entity = world.create_entity().entity();
+
+        let mut queue = CommandQueue::new();
+        let mut commands = Commands::new(&mut queue, &world);
+        commands
+            .despawn(entity)
+            .insert(entity, Health(1))
+            .remove::<Dead>(entity);
+        let spawned = commands.spawn();
+        commands.insert(spawned, Health(2));
+
+        let errors = queue.apply(&mut world);
+
+        let failed: Vec<_> = errors
+            .iter()
+            .map(|error| (error.index, error.kind))
+            .collect();
+        assert_eq!(failed, vec![(1, "insert"), (2, "remove")]);
+        assert!(world.is_alive(spawned));
+        Ok(())
+    }
+}
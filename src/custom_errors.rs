@@ -8,8 +8,42 @@ pub enum CustomErrors {
     ComponentNotRegistered,
     #[error("attempting to reference a entity that doesn't exist")]
     EntityDoesNotExits,
+    #[error("attempting to reference a entity that has already been deleted")]
+    StaleEntity,
     #[error("attempting to reference a component that doesn't exist")]
     ComponentDoesNotExists,
     #[error("attempting to downcast to a wrong type")]
     DowncastsToWrongType,
+    #[error("attempting to reference a resource that doesn't exist")]
+    ResourceDoesNotExists,
+    #[error("conflicting access to {0}, it is borrowed mutably alongside another borrow")]
+    ConflictingAccess(String),
+    #[error("attempting to borrow a component that is already mutably borrowed, or mutably borrow one that is already borrowed")]
+    ComponentAlreadyBorrowed,
+    #[error("systems have to run in a cycle: {0}")]
+    SystemOrderCycle(String),
+    #[error("commands failed to apply: {0}")]
+    CommandsFailed(String),
+    #[error("invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+    #[error("snapshot version {0} is not supported")]
+    UnsupportedSnapshotVersion(u16),
+    #[error("no serializer registered under the name {0}")]
+    SerializerNotRegistered(String),
+    #[error("{0} has to be registered for rollback before a snapshot can be taken")]
+    ComponentNotRegisteredForRollback(&'static str),
+    #[error("there is no rollback snapshot for tick {0}")]
+    RollbackTickNotFound(u32),
+    #[error("an entity can't become a child of itself or of one of its descendants")]
+    HierarchyCycle,
+    #[error("attempting to remove a child from an entity that isn't its parent")]
+    NotAChild,
+    #[error("bundle {0} writes components that don't match the ones its component_ids lists")]
+    BundleMismatch(&'static str),
+    #[error("{line}:{column}: {message}")]
+    Scene {
+        line: usize,
+        column: usize,
+        message: String,
+    },
 }
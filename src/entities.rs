@@ -1,121 +1,614 @@
+pub mod bit_set;
+pub mod bundle;
+pub mod component_ref;
+pub mod entity;
+pub mod hooks;
 pub mod query;
 pub mod query_entity;
+pub mod removed_components;
 
 use std::{
-    any::{Any, TypeId},
-    cell::RefCell,
+    any::{type_name, Any, TypeId},
     collections::HashMap,
-    rc::Rc,
+    mem,
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use eyre::Result;
 
-use crate::custom_errors::CustomErrors;
+use crate::{commands::CommandQueue, commands::Commands, custom_errors::CustomErrors};
 
-pub type Component = Rc<RefCell<dyn Any>>;
-pub type Components = HashMap<TypeId, Vec<Option<Component>>>;
+use self::{
+    bit_set::BitSet,
+    bundle::{Bundle, BundleWriter},
+    component_ref::ComponentCell,
+    entity::Entity,
+    hooks::{ComponentHooks, HookKind, Hooks},
+    removed_components::RemovedLog,
+};
+
+pub type Component = Arc<ComponentCell<dyn Any + Send + Sync>>;
+pub type Components = HashMap<TypeId, Vec<Option<ComponentEntry>>>;
+
+/// A stored component next to the change ticks it was inserted and last written through a
+/// `ComponentMut` at.
+#[derive(Debug)]
+pub struct ComponentEntry {
+    pub component: Component,
+    pub added_tick: u32,
+    pub changed_tick: AtomicU32,
+}
+
+impl ComponentEntry {
+    pub fn new(component: Component, tick: u32) -> Self {
+        Self {
+            component,
+            added_tick: tick,
+            changed_tick: AtomicU32::new(tick),
+        }
+    }
+
+    pub fn changed_tick(&self) -> u32 {
+        self.changed_tick.load(Ordering::Relaxed)
+    }
+
+    pub fn mark_changed(&self, tick: u32) {
+        self.changed_tick.store(tick, Ordering::Relaxed);
+    }
+}
+
+/// Which slots exist, which are alive and which components each of them has; everything about
+/// the entities apart from the component data itself.
+#[derive(Debug, Clone)]
+pub struct EntityLayout {
+    map: Vec<BitSet>,
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free_indices: Vec<usize>,
+    inserting_into_index: usize,
+}
+
+impl EntityLayout {
+    pub fn slots(&self) -> usize {
+        self.map.len()
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct Entities {
     components: Components,
-    bit_masks: HashMap<TypeId, u32>,
-    map: Vec<u32>,
+    change_tick: u32,
+    component_bits: HashMap<TypeId, usize>,
+    map: Vec<BitSet>,
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free_indices: Vec<usize>,
     inserting_into_index: usize,
+    removed: HashMap<TypeId, RemovedLog>,
+    reserved: AtomicUsize,
+    component_names: HashMap<TypeId, &'static str>,
+    hooks: HashMap<TypeId, Hooks>,
+    hook_commands: CommandQueue,
 }
 
 impl Entities {
+    /// Registering a type that is already registered does nothing.
     pub fn register_component<T: Any>(&mut self) {
         let type_id = TypeId::of::<T>();
-        self.components.insert(type_id, vec![]);
-        self.bit_masks.insert(type_id, 1 << self.bit_masks.len());
+        if self.component_bits.contains_key(&type_id) {
+            return;
+        }
+
+        self.flush_reserved();
+        let mut components = Vec::with_capacity(self.map.len());
+        components.resize_with(self.map.len(), || None);
+        self.components.insert(type_id, components);
+        let bit = self.component_bits.len();
+        self.component_bits.insert(type_id, bit);
+        self.component_names.insert(type_id, type_name::<T>());
+    }
+
+    pub fn register_component_with_hooks<T: Any>(&mut self, hooks: ComponentHooks<T>) {
+        self.register_component::<T>();
+        self.hooks.insert(TypeId::of::<T>(), hooks.into_hooks());
+    }
+
+    pub(crate) fn has_hooks(&self, type_id: &TypeId) -> bool {
+        self.hooks.contains_key(type_id)
+    }
+
+    /// Hands over the commands component hooks queued since the last call.
+    pub(crate) fn take_hook_commands(&mut self) -> CommandQueue {
+        mem::take(&mut self.hook_commands)
     }
 
     pub fn create_entity(&mut self) -> &mut Self {
-        if let Some((index, _)) = self
-            .map
-            .iter()
-            .enumerate()
-            .find(|(_index, mask)| **mask == 0)
-        {
+        self.flush_reserved();
+        if let Some(index) = self.free_indices.pop() {
+            self.alive[index] = true;
             self.inserting_into_index = index;
         } else {
             self.components
                 .iter_mut()
                 .for_each(|(_key, component)| component.push(None));
 
-            self.map.push(0);
+            self.map.push(BitSet::new());
+            self.generations.push(0);
+            self.alive.push(true);
             self.inserting_into_index = self.map.len() - 1;
         }
 
         self
     }
 
-    pub fn with_component(&mut self, data: impl Any) -> Result<&mut Self> {
-        let type_id = data.type_id();
+    /// Hands out the handle of an entity that doesn't exist yet, without needing `&mut self`.
+    /// Reserved entities always get a fresh slot; they come alive through `spawn_reserved`, and a
+    /// reservation that is never spawned keeps its slot unused.
+    pub fn reserve_entity(&self) -> Entity {
+        let index = self.map.len() + self.reserved.fetch_add(1, Ordering::Relaxed);
+        Entity::new(index, 0)
+    }
+
+    /// Brings a reserved entity to life and makes it the one `with_component` adds to.
+    pub fn spawn_reserved(&mut self, entity: Entity) -> Result<&mut Self> {
+        self.flush_reserved();
+        if entity.index >= self.map.len() {
+            return Err(CustomErrors::EntityDoesNotExits.into());
+        }
+        if self.generations[entity.index] != entity.generation || self.alive[entity.index] {
+            return Err(CustomErrors::StaleEntity.into());
+        }
+
+        self.alive[entity.index] = true;
+        self.inserting_into_index = entity.index;
+        Ok(self)
+    }
+
+    /// Sets up empty storage with one dead slot per generation, the way a snapshot saved them, so
+    /// handles that were stale before stay stale. The slots still dead once every entity is
+    /// restored become free through `free_dead_slots`.
+    pub(crate) fn restore_slots(&mut self, generations: &[u32]) {
+        self.flush_reserved();
+        for (index, generation) in generations.iter().enumerate() {
+            self.push_dead_slot();
+            self.generations[index] = *generation;
+        }
+    }
+
+    pub(crate) fn free_dead_slots(&mut self) {
+        self.free_indices = (0..self.map.len())
+            .rev()
+            .filter(|index| !self.alive[*index])
+            .collect();
+    }
+
+    /// Brings a slot set up by `restore_slots` back to life under its saved generation.
+    pub(crate) fn restore_entity(&mut self, index: usize) -> Result<Entity> {
+        if index >= self.map.len() {
+            return Err(CustomErrors::EntityDoesNotExits.into());
+        }
+        if self.alive[index] {
+            return Err(CustomErrors::StaleEntity.into());
+        }
+
+        self.alive[index] = true;
+        self.inserting_into_index = index;
+        Ok(self.entity_at(index))
+    }
+
+    /// Gives every reserved entity its slot, still dead, so the storage can grow past them.
+    fn flush_reserved(&mut self) {
+        let reserved = std::mem::take(self.reserved.get_mut());
+        for _ in 0..reserved {
+            self.push_dead_slot();
+        }
+    }
+
+    fn push_dead_slot(&mut self) {
+        self.components
+            .iter_mut()
+            .for_each(|(_key, component)| component.push(None));
+
+        self.map.push(BitSet::new());
+        self.generations.push(0);
+        self.alive.push(false);
+    }
+
+    /// The handle of the entity that `create_entity` most recently handed out.
+    pub fn entity(&self) -> Entity {
         let index = self.inserting_into_index;
+        Entity::new(index, self.generations[index])
+    }
 
-        if let Some(components) = self.components.get_mut(&type_id) {
-            let component = components
-                .get_mut(index)
-                .ok_or(CustomErrors::CreatComponentNeverCalled)?;
-            *component = Some(Rc::new(RefCell::new(data)) as Component);
+    pub fn with_component(&mut self, data: impl Any + Send + Sync) -> Result<&mut Self> {
+        let type_id = data.type_id();
+        let index = self.inserting_into_index;
 
-            let bitmask = self.bit_masks.get(&type_id).unwrap();
-            self.map[index] |= *bitmask;
+        if let Some(components) = self.components.get(&type_id) {
+            if index >= components.len() {
+                return Err(CustomErrors::CreatComponentNeverCalled.into());
+            }
+            self.store_component(index, type_id, Arc::new(ComponentCell::new(data)))?;
         } else {
             return Err(CustomErrors::ComponentNotRegistered.into());
         }
         Ok(self)
     }
 
-    pub fn get_bitmask(&self, type_id: &TypeId) -> Option<u32> {
-        if let Some(bit_mask) = self.bit_masks.get(type_id) {
-            Some(*bit_mask)
-        } else {
-            None
+    /// Adds a whole bundle to the entity `create_entity` handed out.
+    pub fn with_bundle<B: Bundle>(&mut self, bundle: B) -> Result<&mut Self> {
+        let bits = self.bundle_bits::<B>()?;
+        let components = Self::bundle_components(bundle, &bits)?;
+        self.write_bundle(self.inserting_into_index, &bits, components)?;
+        Ok(self)
+    }
+
+    /// Creates an entity holding the bundle. Nothing is created when one of its components isn't
+    /// registered.
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Result<Entity> {
+        let bits = self.bundle_bits::<B>()?;
+        let components = Self::bundle_components(bundle, &bits)?;
+        self.create_entity();
+        self.write_bundle(self.inserting_into_index, &bits, components)?;
+        Ok(self.entity())
+    }
+
+    /// Creates one entity per bundle, reusing deleted slots first. Every column is grown once
+    /// and then filled for the whole batch, instead of once per entity.
+    pub fn spawn_batch<B: Bundle>(
+        &mut self,
+        bundles: impl IntoIterator<Item = B>,
+    ) -> Result<Vec<Entity>> {
+        let bits = self.bundle_bits::<B>()?;
+        self.flush_reserved();
+
+        let mut rows = bundles
+            .into_iter()
+            .map(|bundle| Ok(Self::bundle_components(bundle, &bits)?.into_iter()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let reused = rows.len().min(self.free_indices.len());
+        let mut indices = self
+            .free_indices
+            .split_off(self.free_indices.len() - reused);
+        indices.reverse();
+        let start = self.map.len();
+        let end = start + rows.len() - reused;
+        indices.extend(start..end);
+
+        self.map.resize_with(end, BitSet::new);
+        self.generations.resize(end, 0);
+        self.alive.resize(end, false);
+        self.components
+            .values_mut()
+            .for_each(|column| column.resize_with(end, || None));
+
+        let signature: BitSet = bits.iter().map(|(_type_id, bit)| *bit).collect();
+        for index in &indices {
+            self.alive[*index] = true;
+            self.map[*index] = signature.clone();
+        }
+
+        let tick = self.change_tick;
+        for (type_id, _bit) in &bits {
+            let column = self.components.get_mut(type_id).unwrap();
+            for (row, index) in rows.iter_mut().zip(&indices) {
+                column[*index] = Some(ComponentEntry::new(row.next().unwrap(), tick));
+            }
+        }
+
+        if let Some(last) = indices.last() {
+            self.inserting_into_index = *last;
+        }
+
+        if bits
+            .iter()
+            .any(|(type_id, _bit)| self.hooks.contains_key(type_id))
+        {
+            for index in &indices {
+                for (type_id, _bit) in &bits {
+                    self.run_insert_hooks(*type_id, *index, true)?;
+                }
+            }
+        }
+        Ok(indices
+            .into_iter()
+            .map(|index| self.entity_at(index))
+            .collect())
+    }
+
+    pub fn insert_bundle<B: Bundle>(&mut self, entity: Entity, bundle: B) -> Result<()> {
+        let index = self.index_of(entity)?;
+        let bits = self.bundle_bits::<B>()?;
+        let components = Self::bundle_components(bundle, &bits)?;
+        self.write_bundle(index, &bits, components)
+    }
+
+    /// Removes whichever components of the bundle the entity has. Every remove hook runs before
+    /// anything is removed, so a hook that fails leaves the entity as it was.
+    pub fn remove_bundle<B: Bundle>(&mut self, entity: Entity) -> Result<()> {
+        let index = self.index_of(entity)?;
+        let present: Vec<(TypeId, usize)> = self
+            .bundle_bits::<B>()?
+            .into_iter()
+            .filter(|(_type_id, bit)| self.has_component(index, *bit))
+            .collect();
+
+        for (type_id, _bit) in &present {
+            self.run_hook(HookKind::Remove, *type_id, index)?;
+        }
+
+        let mut signature = BitSet::new();
+        for (type_id, bit) in present {
+            self.components.get_mut(&type_id).unwrap()[index] = None;
+            self.removed.entry(type_id).or_default().push(entity);
+            signature.insert(bit);
+        }
+        self.map[index].difference_with(&signature);
+        Ok(())
+    }
+
+    /// Looks up every component of the bundle before anything is touched, so an unregistered one
+    /// leaves the entity as it was.
+    fn bundle_bits<B: Bundle>(&self) -> Result<Vec<(TypeId, usize)>> {
+        let mut type_ids = Vec::new();
+        B::component_ids(&mut type_ids);
+        type_ids
+            .into_iter()
+            .map(|type_id| match self.component_bits.get(&type_id) {
+                Some(bit) => Ok((type_id, *bit)),
+                None => Err(CustomErrors::ComponentNotRegistered.into()),
+            })
+            .collect()
+    }
+
+    fn bundle_components<B: Bundle>(bundle: B, bits: &[(TypeId, usize)]) -> Result<Vec<Component>> {
+        let mut writer = BundleWriter::default();
+        bundle.write_components(&mut writer);
+        writer.into_components::<B>(bits)
+    }
+
+    fn write_bundle(
+        &mut self,
+        index: usize,
+        bits: &[(TypeId, usize)],
+        components: Vec<Component>,
+    ) -> Result<()> {
+        let mut signature = BitSet::new();
+        let mut added = Vec::with_capacity(bits.len());
+        for ((type_id, bit), component) in bits.iter().zip(components) {
+            added.push(self.store_entry(index, *type_id, component));
+            signature.insert(*bit);
+        }
+        self.map[index].union_with(&signature);
+
+        for ((type_id, _bit), added) in bits.iter().zip(added) {
+            self.run_insert_hooks(*type_id, index, added)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_bit(&self, type_id: &TypeId) -> Option<usize> {
+        self.component_bits.get(type_id).copied()
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.generations.get(entity.index) == Some(&entity.generation) && self.alive[entity.index]
+    }
+
+    /// Turns a handle back into a slot index, rejecting handles whose entity has since been deleted.
+    pub fn index_of(&self, entity: Entity) -> Result<usize> {
+        if entity.index >= self.generations.len() {
+            return Err(CustomErrors::EntityDoesNotExits.into());
+        }
+
+        if !self.is_alive(entity) {
+            return Err(CustomErrors::StaleEntity.into());
         }
+
+        Ok(entity.index)
+    }
+
+    pub(crate) fn entity_at(&self, index: usize) -> Entity {
+        Entity::new(index, self.generations[index])
+    }
+
+    pub(crate) fn generations(&self) -> &[u32] {
+        &self.generations
+    }
+
+    pub(crate) fn live_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        (0..self.map.len())
+            .filter(|index| self.alive[*index])
+            .map(|index| self.entity_at(index))
+    }
+
+    pub(crate) fn component(&self, type_id: &TypeId, index: usize) -> Option<&ComponentEntry> {
+        self.components.get(type_id)?.get(index)?.as_ref()
     }
 
-    pub fn delete_component_by_entity_id<T: Any>(&mut self, index: usize) -> Result<()> {
+    pub(crate) fn component_types(&self) -> impl Iterator<Item = &TypeId> {
+        self.components.keys()
+    }
+
+    pub(crate) fn component_name(&self, type_id: &TypeId) -> &'static str {
+        self.component_names
+            .get(type_id)
+            .copied()
+            .unwrap_or("unknown")
+    }
+
+    pub(crate) fn column(&self, type_id: &TypeId) -> Option<&[Option<ComponentEntry>]> {
+        self.components.get(type_id).map(Vec::as_slice)
+    }
+
+    /// Swaps in a whole column, which has to cover every slot of the layout it goes with.
+    pub(crate) fn replace_column(&mut self, type_id: TypeId, column: Vec<Option<ComponentEntry>>) {
+        self.components.insert(type_id, column);
+    }
+
+    pub(crate) fn layout(&mut self) -> EntityLayout {
+        self.flush_reserved();
+        EntityLayout {
+            map: self.map.clone(),
+            generations: self.generations.clone(),
+            alive: self.alive.clone(),
+            free_indices: self.free_indices.clone(),
+            inserting_into_index: self.inserting_into_index,
+        }
+    }
+
+    /// Puts back a layout taken with `layout`. The columns have to be replaced to match it.
+    pub(crate) fn restore_layout(&mut self, layout: &EntityLayout) {
+        *self.reserved.get_mut() = 0;
+        self.map = layout.map.clone();
+        self.generations = layout.generations.clone();
+        self.alive = layout.alive.clone();
+        self.free_indices = layout.free_indices.clone();
+        self.inserting_into_index = layout.inserting_into_index;
+    }
+
+    pub fn delete_component_by_entity_id<T: Any>(&mut self, entity: Entity) -> Result<()> {
+        let index = self.index_of(entity)?;
         let type_id = TypeId::of::<T>();
-        let mask = if let Some(mask) = self.bit_masks.get(&type_id) {
-            mask
+        let bit = if let Some(bit) = self.component_bits.get(&type_id) {
+            *bit
         } else {
             return Err(CustomErrors::ComponentNotRegistered.into());
         };
 
-        if self.has_component(index, *mask) {
-            self.map[index] ^= *mask;
+        if self.has_component(index, bit) {
+            self.run_hook(HookKind::Remove, type_id, index)?;
+            self.map[index].remove(bit);
+            self.components.get_mut(&type_id).unwrap()[index] = None;
+            self.removed.entry(type_id).or_default().push(entity);
         }
 
         Ok(())
     }
 
-    pub fn add_component_by_entity_id(&mut self, data: impl Any, index: usize) -> Result<()> {
+    pub fn add_component_by_entity_id(
+        &mut self,
+        data: impl Any + Send + Sync,
+        entity: Entity,
+    ) -> Result<()> {
+        let index = self.index_of(entity)?;
         let type_id = data.type_id();
-        let mask = if let Some(mask) = self.bit_masks.get(&type_id) {
-            mask
-        } else {
+        if !self.component_bits.contains_key(&type_id) {
             return Err(CustomErrors::ComponentNotRegistered.into());
-        };
+        }
+
+        self.store_component(index, type_id, Arc::new(ComponentCell::new(data)))
+    }
+
+    fn store_component(
+        &mut self,
+        index: usize,
+        type_id: TypeId,
+        component: Component,
+    ) -> Result<()> {
+        let added = self.store_entry(index, type_id, component);
+        let bit = self.component_bits[&type_id];
+        self.map[index].insert(bit);
+        self.run_insert_hooks(type_id, index, added)
+    }
+
+    /// Replacing a component the entity already has only counts as a change, not an addition.
+    /// Leaves the entity's bits and the hooks to the caller; returns whether it was added.
+    fn store_entry(&mut self, index: usize, type_id: TypeId, component: Component) -> bool {
+        let tick = self.change_tick;
+        let entry = &mut self.components.get_mut(&type_id).unwrap()[index];
+        match entry {
+            Some(entry) => {
+                entry.component = component;
+                entry.mark_changed(tick);
+                false
+            }
+            None => {
+                *entry = Some(ComponentEntry::new(component, tick));
+                true
+            }
+        }
+    }
 
-        self.map[index] |= *mask;
-        let component = self.components.get_mut(&type_id).unwrap();
-        component[index] = Some(Rc::new(RefCell::new(data)) as Rc<RefCell<dyn Any>>);
+    fn run_insert_hooks(&mut self, type_id: TypeId, index: usize, added: bool) -> Result<()> {
+        if added {
+            self.run_hook(HookKind::Add, type_id, index)?;
+        }
+        self.run_hook(HookKind::Insert, type_id, index)
+    }
+
+    /// Calls the hook with the component as it is stored right now, queueing whatever commands
+    /// it records.
+    fn run_hook(&mut self, kind: HookKind, type_id: TypeId, index: usize) -> Result<()> {
+        let Some(hook) = self.hooks.get(&type_id).and_then(|hooks| hooks.get(kind)) else {
+            return Ok(());
+        };
+        let hook = hook.clone();
+        let cell = self.component(&type_id, index).unwrap().component.clone();
+        let component = cell.read()?;
+
+        let mut queue = mem::take(&mut self.hook_commands);
+        hook(
+            &*component,
+            self.entity_at(index),
+            &mut Commands::for_entities(&mut queue, self),
+        );
+        self.hook_commands = queue;
         Ok(())
     }
 
-    pub fn delete_entity_by_id(&mut self, index: usize) -> Result<()> {
-        if let Some(map) = self.map.get_mut(index) {
-            *map = 0u32;
-        } else {
-            return Err(CustomErrors::EntityDoesNotExits.into());
+    /// Forget every recorded removal. Readers that haven't caught up yet will miss them.
+    pub fn clear_removed(&mut self) {
+        self.removed.values_mut().for_each(RemovedLog::clear);
+    }
+
+    /// Forget the removals that are two updates old.
+    pub fn update_removed(&mut self) {
+        self.removed.values_mut().for_each(RemovedLog::update);
+    }
+
+    pub fn change_tick(&self) -> u32 {
+        self.change_tick
+    }
+
+    pub fn increment_change_tick(&mut self) -> u32 {
+        self.change_tick += 1;
+        self.change_tick
+    }
+
+    pub fn delete_entity_by_id(&mut self, entity: Entity) -> Result<()> {
+        let index = self.index_of(entity)?;
+
+        let mut hooked: Vec<(usize, TypeId)> = self
+            .hooks
+            .keys()
+            .map(|type_id| (self.component_bits[type_id], *type_id))
+            .filter(|(bit, _type_id)| self.has_component(index, *bit))
+            .collect();
+        hooked.sort();
+        for (_bit, type_id) in hooked {
+            self.run_hook(HookKind::Remove, type_id, index)?;
+        }
+
+        self.map[index].clear();
+        for (type_id, components) in self.components.iter_mut() {
+            if components[index].take().is_some() {
+                self.removed.entry(*type_id).or_default().push(entity);
+            }
         }
+
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.alive[index] = false;
+        self.free_indices.push(index);
         Ok(())
     }
 
-    fn has_component(&self, index: usize, mask: u32) -> bool {
-        self.map[index] & mask == mask
+    fn has_component(&self, index: usize, bit: usize) -> bool {
+        self.map[index].contains(bit)
     }
 }
 
@@ -139,13 +632,13 @@ mod test {
         let mut entities = Entities::default();
         entities.register_component::<Health>();
         let type_id = TypeId::of::<Health>();
-        let health_mask = entities.bit_masks.get(&type_id).unwrap();
-        assert_eq!(*health_mask, 1);
+        let health_bit = entities.component_bits.get(&type_id).unwrap();
+        assert_eq!(*health_bit, 0);
 
         entities.register_component::<Speed>();
         let type_id = TypeId::of::<Speed>();
-        let speed_mask = entities.bit_masks.get(&type_id).unwrap();
-        assert_eq!(*speed_mask, 2);
+        let speed_bit = entities.component_bits.get(&type_id).unwrap();
+        assert_eq!(*speed_bit, 1);
     }
 
     #[test]
@@ -176,8 +669,8 @@ mod test {
             .unwrap();
 
         let first_health = &entities.components.get(&TypeId::of::<Health>()).unwrap()[0];
-        let wrapped_health = first_health.as_ref().unwrap();
-        let borrowed_health = wrapped_health.borrow();
+        let wrapped_health = &first_health.as_ref().unwrap().component;
+        let borrowed_health = wrapped_health.read().unwrap();
         let health = borrowed_health.downcast_ref::<Health>().unwrap();
 
         assert_eq!(health.0, 100);
@@ -195,13 +688,13 @@ mod test {
             .with_component(Health(100))?
             .with_component(Speed(15))?;
 
-        let entity_map = entities.map[0];
-        assert_eq!(entity_map, 3);
+        let entity_map = &entities.map[0];
+        assert_eq!(*entity_map, bits(&[0, 1]));
 
         entities.create_entity().with_component(Speed(15))?;
 
-        let entity_map = entities.map[1];
-        assert_eq!(entity_map, 2);
+        let entity_map = &entities.map[1];
+        assert_eq!(*entity_map, bits(&[1]));
 
         Ok(())
     }
@@ -213,15 +706,16 @@ mod test {
         entities.register_component::<Health>();
         entities.register_component::<Speed>();
 
-        entities
+        let entity = entities
             .create_entity()
             .with_component(Health(100))?
-            .with_component(Speed(15))?;
+            .with_component(Speed(15))?
+            .entity();
 
-        entities.delete_component_by_entity_id::<Health>(0)?;
+        entities.delete_component_by_entity_id::<Health>(entity)?;
 
-        let entity_map = entities.map[0];
-        assert_eq!(entity_map, 2);
+        let entity_map = &entities.map[0];
+        assert_eq!(*entity_map, bits(&[1]));
 
         Ok(())
     }
@@ -233,20 +727,23 @@ mod test {
         entities.register_component::<Health>();
         entities.register_component::<Speed>();
 
-        entities.create_entity().with_component(Health(100))?;
-        let entity_map = entities.map[0];
-        assert_eq!(entity_map, 1);
+        let entity = entities
+            .create_entity()
+            .with_component(Health(100))?
+            .entity();
+        let entity_map = &entities.map[0];
+        assert_eq!(*entity_map, bits(&[0]));
 
-        entities.add_component_by_entity_id(Speed(50), 0)?;
+        entities.add_component_by_entity_id(Speed(50), entity)?;
 
-        let entity_map = entities.map[0];
-        assert_eq!(entity_map, 3);
+        let entity_map = &entities.map[0];
+        assert_eq!(*entity_map, bits(&[0, 1]));
 
         let speed_type_id = TypeId::of::<Speed>();
 
         let wrapped_speeds = entities.components.get(&speed_type_id).unwrap();
-        let wrapped_speed = wrapped_speeds[0].as_ref().unwrap();
-        let borrwed_speed = wrapped_speed.borrow();
+        let wrapped_speed = &wrapped_speeds[0].as_ref().unwrap().component;
+        let borrwed_speed = wrapped_speed.read().unwrap();
         let speed = borrwed_speed.downcast_ref::<Speed>().unwrap();
         assert_eq!(speed.0, 50);
 
@@ -260,10 +757,14 @@ mod test {
         entities.register_component::<Health>();
         entities.register_component::<Speed>();
 
-        entities.create_entity().with_component(Health(100))?;
-        entities.delete_entity_by_id(0)?;
+        let entity = entities
+            .create_entity()
+            .with_component(Health(100))?
+            .entity();
+        entities.delete_entity_by_id(entity)?;
 
-        assert_eq!(entities.map[0], 0);
+        assert_eq!(entities.map[0], BitSet::new());
+        assert!(!entities.is_alive(entity));
         Ok(())
     }
 
@@ -273,20 +774,30 @@ mod test {
 
         entities.register_component::<Health>();
 
-        entities.create_entity().with_component(Health(100))?;
+        let first = entities
+            .create_entity()
+            .with_component(Health(100))?
+            .entity();
         entities.create_entity().with_component(Health(50))?;
 
-        entities.delete_entity_by_id(0)?;
+        entities.delete_entity_by_id(first)?;
 
-        entities.create_entity().with_component(Health(25))?;
+        let reused = entities
+            .create_entity()
+            .with_component(Health(25))?
+            .entity();
 
-        assert_eq!(entities.map[0], 1);
+        assert_eq!(entities.map[0], bits(&[0]));
+        assert_eq!(reused.index, first.index);
+        assert_ne!(reused.generation, first.generation);
 
         let type_id = TypeId::of::<Health>();
         let borrowed_health = &entities.components.get(&type_id).unwrap()[0]
             .as_ref()
             .unwrap()
-            .borrow();
+            .component
+            .read()
+            .unwrap();
         let health = borrowed_health.downcast_ref::<Health>().unwrap();
 
         assert_eq!(health.0, 25);
@@ -300,18 +811,227 @@ mod test {
         entities.register_component::<u32>();
         entities.register_component::<f32>();
 
-        entities
+        let entity = entities
             .create_entity()
             .with_component(100_u32)?
-            .with_component(50.0_f32)?;
+            .with_component(50.0_f32)?
+            .entity();
+
+        entities.delete_component_by_entity_id::<u32>(entity)?;
+        entities.delete_component_by_entity_id::<u32>(entity)?;
+        assert_eq!(entities.map[0], bits(&[1]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stale_entity_is_rejected() -> Result<()> {
+        let mut entities = Entities::default();
+        entities.register_component::<Health>();
 
-        entities.delete_component_by_entity_id::<u32>(0)?;
-        entities.delete_component_by_entity_id::<u32>(0)?;
-        assert_eq!(entities.map[0], 2);
+        let stale = entities
+            .create_entity()
+            .with_component(Health(100))?
+            .entity();
+        entities.delete_entity_by_id(stale)?;
+        entities.create_entity().with_component(Health(50))?;
 
+        let error = entities
+            .add_component_by_entity_id(Health(1), stale)
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<CustomErrors>(),
+            Some(CustomErrors::StaleEntity)
+        ));
+        assert!(entities.delete_entity_by_id(stale).is_err());
+        assert_eq!(entities.map[0], bits(&[0]));
         Ok(())
     }
 
+    #[test]
+    fn entity_that_was_never_created_does_not_exist() {
+        let mut entities = Entities::default();
+        entities.register_component::<Health>();
+
+        let error = entities.delete_entity_by_id(Entity::new(3, 0)).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<CustomErrors>(),
+            Some(CustomErrors::EntityDoesNotExits)
+        ));
+    }
+
+    #[test]
+    fn replacing_a_component_keeps_its_added_tick() -> Result<()> {
+        let mut entities = Entities::default();
+        entities.register_component::<Health>();
+
+        let entity = entities
+            .create_entity()
+            .with_component(Health(100))?
+            .entity();
+        entities.increment_change_tick();
+        entities.add_component_by_entity_id(Health(50), entity)?;
+
+        let entry = entities.components[&TypeId::of::<Health>()][0]
+            .as_ref()
+            .unwrap();
+        assert_eq!(entry.added_tick, 0);
+        assert_eq!(entry.changed_tick(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn registering_more_than_32_components() -> Result<()> {
+        let mut entities = Entities::default();
+        register_markers(&mut entities);
+        entities.register_component::<Health>();
+
+        entities
+            .create_entity()
+            .with_component(Marker::<69>)?
+            .with_component(Health(10))?;
+
+        assert_eq!(entities.get_bit(&TypeId::of::<Health>()), Some(70));
+        assert_eq!(entities.map[0], bits(&[69, 70]));
+        Ok(())
+    }
+
+    #[test]
+    fn reserved_entities_keep_their_slot() -> Result<()> {
+        let mut entities = Entities::default();
+        entities.register_component::<Health>();
+        let existing = entities.create_entity().entity();
+
+        let reserved = entities.reserve_entity();
+        let created = entities.create_entity().entity();
+        assert_eq!(reserved, Entity::new(1, 0));
+        assert_eq!(created, Entity::new(2, 0));
+        assert!(!entities.is_alive(reserved));
+
+        entities
+            .spawn_reserved(reserved)?
+            .with_component(Health(1))?;
+        assert!(entities.is_alive(reserved));
+        assert_eq!(entities.map[1], bits(&[0]));
+        assert!(entities.spawn_reserved(reserved).is_err());
+        assert!(entities.spawn_reserved(existing).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bundles_update_the_map_in_one_go() -> Result<()> {
+        let mut entities = Entities::default();
+        register_markers(&mut entities);
+        entities.register_component::<Health>();
+        entities.register_component::<Speed>();
+
+        let entity = entities.spawn((Marker::<3>, Health(1)))?;
+        assert_eq!(entities.map[0], bits(&[3, 70]));
+
+        entities.insert_bundle(entity, (Speed(2), Marker::<68>))?;
+        assert_eq!(entities.map[0], bits(&[3, 68, 70, 71]));
+
+        entities.remove_bundle::<(Health, Marker<3>, Marker<4>)>(entity)?;
+        assert_eq!(entities.map[0], bits(&[68, 71]));
+        assert!(entities.component(&TypeId::of::<Health>(), 0).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn batches_reuse_free_slots_first() -> Result<()> {
+        let mut entities = Entities::default();
+        entities.register_component::<Health>();
+        entities.register_component::<Speed>();
+        let first = entities.spawn((Health(1),))?;
+        let second = entities.spawn((Health(2),))?;
+        entities.delete_entity_by_id(first)?;
+        entities.delete_entity_by_id(second)?;
+
+        let batch = entities.spawn_batch((3..6).map(|speed| (Speed(speed), Health(speed))))?;
+
+        assert_eq!(
+            batch,
+            vec![Entity::new(1, 1), Entity::new(0, 1), Entity::new(2, 0)]
+        );
+        assert!(entities.free_indices.is_empty());
+        assert_eq!(entities.map, vec![bits(&[0, 1]); 3]);
+        assert!(entities
+            .components
+            .values()
+            .all(|column| column.len() == 3 && column.iter().all(Option::is_some)));
+        assert_eq!(entities.entity(), Entity::new(2, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn registering_twice_keeps_the_bit_and_column() -> Result<()> {
+        let mut entities = Entities::default();
+        entities.register_component::<Health>();
+        entities.register_component::<Speed>();
+        let entity = entities.create_entity().with_component(Health(3))?.entity();
+
+        entities.register_component::<Health>();
+        entities.register_component::<Marker<0>>();
+
+        assert_eq!(entities.get_bit(&TypeId::of::<Health>()), Some(0));
+        assert_eq!(entities.get_bit(&TypeId::of::<Marker<0>>()), Some(2));
+        assert!(entities
+            .component(&TypeId::of::<Health>(), entity.index)
+            .is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn failing_remove_hook_leaves_the_bundle_in_place() -> Result<()> {
+        let mut entities = Entities::default();
+        entities.register_component_with_hooks(ComponentHooks::<Health>::new().on_remove(
+            |_health, entity, commands| {
+                commands.despawn(entity);
+            },
+        ));
+        entities.register_component_with_hooks(
+            ComponentHooks::<Speed>::new().on_remove(|_speed, _entity, _commands| {}),
+        );
+        let entity = entities.spawn((Health(1), Speed(2)))?;
+
+        let speed = entities
+            .component(&TypeId::of::<Speed>(), entity.index)
+            .unwrap()
+            .component
+            .clone();
+        let guard = speed.write()?;
+        assert!(entities.remove_bundle::<(Health, Speed)>(entity).is_err());
+        drop(guard);
+
+        assert_eq!(entities.map[entity.index], bits(&[0, 1]));
+        assert!(entities
+            .component(&TypeId::of::<Health>(), entity.index)
+            .is_some());
+        assert!(entities
+            .component(&TypeId::of::<Speed>(), entity.index)
+            .is_some());
+        Ok(())
+    }
+
+    fn bits(bits: &[usize]) -> BitSet {
+        bits.iter().copied().collect()
+    }
+
+    struct Marker<const N: usize>;
+
+    fn register_markers(entities: &mut Entities) {
+        macro_rules! register {
+            ($($n:literal)*) => {
+                $(entities.register_component::<Marker<$n>>();)*
+            };
+        }
+        register!(
+            0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+            33 34 35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60 61
+            62 63 64 65 66 67 68 69
+        );
+    }
+
     struct Health(pub u32);
     struct Speed(pub u32);
 }
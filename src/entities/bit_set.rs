@@ -0,0 +1,145 @@
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A growable set of component bits. Words are only allocated up to the highest bit that was
+/// ever inserted, so a signature is as small as the components it actually holds.
+#[derive(Debug, Clone, Default)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, bit: usize) {
+        let (word, mask) = Self::locate(bit);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= mask;
+    }
+
+    pub fn remove(&mut self, bit: usize) {
+        let (word, mask) = Self::locate(bit);
+        if let Some(word) = self.words.get_mut(word) {
+            *word &= !mask;
+        }
+    }
+
+    pub fn contains(&self, bit: usize) -> bool {
+        let (word, mask) = Self::locate(bit);
+        self.words.get(word).is_some_and(|word| word & mask == mask)
+    }
+
+    /// True when every bit of `other` is also set in `self`.
+    pub fn contains_all(&self, other: &BitSet) -> bool {
+        other
+            .words
+            .iter()
+            .enumerate()
+            .all(|(index, word)| self.words.get(index).copied().unwrap_or(0) & word == *word)
+    }
+
+    pub fn intersects(&self, other: &BitSet) -> bool {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .any(|(left, right)| left & right != 0)
+    }
+
+    pub fn union_with(&mut self, other: &BitSet) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        self.words
+            .iter_mut()
+            .zip(other.words.iter())
+            .for_each(|(word, other)| *word |= other);
+    }
+
+    pub fn difference_with(&mut self, other: &BitSet) {
+        self.words
+            .iter_mut()
+            .zip(other.words.iter())
+            .for_each(|(word, other)| *word &= !other);
+    }
+
+    pub fn clear(&mut self) {
+        self.words.iter_mut().for_each(|word| *word = 0);
+    }
+
+    fn locate(bit: usize) -> (usize, u64) {
+        (bit / WORD_BITS, 1 << (bit % WORD_BITS))
+    }
+}
+
+impl PartialEq for BitSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.contains_all(other) && other.contains_all(self)
+    }
+}
+
+impl Eq for BitSet {}
+
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut bit_set = BitSet::new();
+        iter.into_iter().for_each(|bit| bit_set.insert(bit));
+        bit_set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_past_a_single_word() {
+        let mut bit_set = BitSet::new();
+        bit_set.insert(3);
+        bit_set.insert(200);
+
+        assert!(bit_set.contains(3));
+        assert!(bit_set.contains(200));
+        assert!(!bit_set.contains(199));
+        assert!(!bit_set.contains(5000));
+    }
+
+    #[test]
+    fn contains_all_ignores_missing_words() {
+        let small: BitSet = [1].into_iter().collect();
+        let large: BitSet = [1, 130].into_iter().collect();
+
+        assert!(large.contains_all(&small));
+        assert!(!small.contains_all(&large));
+        assert!(small.contains_all(&BitSet::new()));
+    }
+
+    #[test]
+    fn equality_ignores_trailing_empty_words() {
+        let mut grown: BitSet = [2, 100].into_iter().collect();
+        grown.remove(100);
+
+        assert_eq!(grown, [2].into_iter().collect());
+    }
+
+    #[test]
+    fn intersects_only_on_shared_bits() {
+        let left: BitSet = [0, 64].into_iter().collect();
+
+        assert!(left.intersects(&[64, 300].into_iter().collect()));
+        assert!(!left.intersects(&[1, 300].into_iter().collect()));
+        assert!(!left.intersects(&BitSet::new()));
+    }
+
+    #[test]
+    fn union_and_difference_across_words() {
+        let mut bit_set: BitSet = [1].into_iter().collect();
+        bit_set.union_with(&[2, 150].into_iter().collect());
+        assert_eq!(bit_set, [1, 2, 150].into_iter().collect());
+
+        bit_set.difference_with(&[1, 150, 400].into_iter().collect());
+        assert_eq!(bit_set, [2].into_iter().collect());
+    }
+}
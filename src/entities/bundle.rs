@@ -0,0 +1,118 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    sync::Arc,
+};
+
+use eyre::Result;
+
+use crate::custom_errors::CustomErrors;
+
+use super::{component_ref::ComponentCell, Component};
+
+/// Several components inserted or removed in one go, like `(Location, Velocity, Health)`.
+/// Tuples of up to twelve components are bundles; a struct becomes one by handing its fields
+/// over as a tuple:
+/// ```
+/// use std::any::TypeId;
+/// use ecs_in_rust::{Bundle, BundleWriter, World};
+///
+/// struct Location(f32);
+/// struct Health(u32);
+///
+/// struct Unit {
+///     location: Location,
+///     health: Health,
+/// }
+///
+/// impl Bundle for Unit {
+///     fn component_ids(type_ids: &mut Vec<TypeId>) {
+///         <(Location, Health)>::component_ids(type_ids);
+///     }
+///
+///     fn write_components(self, writer: &mut BundleWriter) {
+///         (self.location, self.health).write_components(writer);
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.register_component::<Location>();
+/// world.register_component::<Health>();
+/// let unit = world.spawn(Unit { location: Location(1.0), health: Health(10) })?;
+///
+/// world.remove_bundle::<Unit>(unit)?;
+/// assert!(world.query::<&Health>()?.iter().next().is_none());
+/// # Ok::<(), eyre::Report>(())
+/// ```
+pub trait Bundle: Send + Sync + 'static {
+    /// The types of the components, in the order `write_components` pushes them. A bundle that
+    /// pushes anything else is rejected with `BundleMismatch` before it touches the world.
+    fn component_ids(type_ids: &mut Vec<TypeId>);
+
+    fn write_components(self, writer: &mut BundleWriter);
+}
+
+/// Collects the components of a bundle on their way into the world.
+#[derive(Default)]
+pub struct BundleWriter {
+    components: Vec<(TypeId, Component)>,
+}
+
+impl BundleWriter {
+    pub fn push<T: Any + Send + Sync>(&mut self, component: T) {
+        self.components
+            .push((TypeId::of::<T>(), Arc::new(ComponentCell::new(component))));
+    }
+
+    /// Hands the components back once they are known to be exactly the ones `B` declared.
+    pub(crate) fn into_components<B: Bundle>(
+        self,
+        bits: &[(TypeId, usize)],
+    ) -> Result<Vec<Component>> {
+        let matches = self.components.len() == bits.len()
+            && self
+                .components
+                .iter()
+                .zip(bits)
+                .all(|((written, _component), (declared, _bit))| written == declared);
+        if !matches {
+            return Err(CustomErrors::BundleMismatch(type_name::<B>()).into());
+        }
+
+        Ok(self
+            .components
+            .into_iter()
+            .map(|(_type_id, component)| component)
+            .collect())
+    }
+}
+
+macro_rules! impl_bundle_for_tuple {
+    ($($component:ident),*) => {
+        impl<$($component: Any + Send + Sync),*> Bundle for ($($component,)*) {
+            #[allow(unused_variables)]
+            fn component_ids(type_ids: &mut Vec<TypeId>) {
+                $(type_ids.push(TypeId::of::<$component>());)*
+            }
+
+            #[allow(non_snake_case, unused_variables)]
+            fn write_components(self, writer: &mut BundleWriter) {
+                let ($($component,)*) = self;
+                $(writer.push($component);)*
+            }
+        }
+    };
+}
+
+impl_bundle_for_tuple!();
+impl_bundle_for_tuple!(A);
+impl_bundle_for_tuple!(A, B);
+impl_bundle_for_tuple!(A, B, C);
+impl_bundle_for_tuple!(A, B, C, D);
+impl_bundle_for_tuple!(A, B, C, D, E);
+impl_bundle_for_tuple!(A, B, C, D, E, F);
+impl_bundle_for_tuple!(A, B, C, D, E, F, G);
+impl_bundle_for_tuple!(A, B, C, D, E, F, G, H);
+impl_bundle_for_tuple!(A, B, C, D, E, F, G, H, I);
+impl_bundle_for_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_bundle_for_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_bundle_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
@@ -0,0 +1,198 @@
+use std::{
+    any::Any,
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+use eyre::Result;
+
+use crate::custom_errors::CustomErrors;
+
+use super::ComponentEntry;
+
+const WRITING: usize = usize::MAX;
+
+/// Shared storage for a single component. Borrows are tracked with an atomic counter instead of a
+/// lock: a borrow that would conflict fails straight away rather than waiting for the other one.
+pub struct ComponentCell<T: ?Sized> {
+    borrows: AtomicUsize,
+    value: UnsafeCell<T>,
+}
+
+// Readers on different threads only ever hand out `&T` and a writer is exclusive, which is the
+// same contract as `RwLock`.
+unsafe impl<T: ?Sized + Send + Sync> Sync for ComponentCell<T> {}
+
+impl<T> ComponentCell<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            borrows: AtomicUsize::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+}
+
+impl<T: ?Sized> ComponentCell<T> {
+    pub fn read(&self) -> Result<CellRef<'_, T>> {
+        let mut borrows = self.borrows.load(Ordering::Relaxed);
+        loop {
+            if borrows >= WRITING - 1 {
+                return Err(CustomErrors::ComponentAlreadyBorrowed.into());
+            }
+            match self.borrows.compare_exchange_weak(
+                borrows,
+                borrows + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Ok(CellRef { cell: self }),
+                Err(current) => borrows = current,
+            }
+        }
+    }
+
+    pub fn write(&self) -> Result<CellMut<'_, T>> {
+        self.borrows
+            .compare_exchange(0, WRITING, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| CustomErrors::ComponentAlreadyBorrowed)?;
+        Ok(CellMut { cell: self })
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for ComponentCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ComponentCell")
+            .field("borrows", &self.borrows)
+            .finish_non_exhaustive()
+    }
+}
+
+pub struct CellRef<'a, T: ?Sized> {
+    cell: &'a ComponentCell<T>,
+}
+
+impl<T: ?Sized> Deref for CellRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // The reader count keeps writers out for as long as this guard lives.
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for CellRef<'_, T> {
+    fn drop(&mut self) {
+        self.cell.borrows.fetch_sub(1, Ordering::Release);
+    }
+}
+
+pub struct CellMut<'a, T: ?Sized> {
+    cell: &'a ComponentCell<T>,
+}
+
+impl<T: ?Sized> Deref for CellMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // This guard is the only borrow until it is dropped.
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for CellMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for CellMut<'_, T> {
+    fn drop(&mut self) {
+        self.cell.borrows.store(0, Ordering::Release);
+    }
+}
+
+/// Shared borrow of a stored component, downcast to `T` on access.
+pub struct ComponentRef<'a, T> {
+    guard: CellRef<'a, dyn Any + Send + Sync>,
+    component: PhantomData<&'a T>,
+}
+
+impl<'a, T: Any> ComponentRef<'a, T> {
+    pub fn new(cell: &'a ComponentCell<dyn Any + Send + Sync>) -> Result<Self> {
+        Ok(Self {
+            guard: cell.read()?,
+            component: PhantomData,
+        })
+    }
+}
+
+impl<T: Any> Deref for ComponentRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.guard.downcast_ref::<T>().unwrap()
+    }
+}
+
+/// Exclusive borrow of a stored component, downcast to `T` on access. The component only counts
+/// as changed once it is actually dereferenced mutably.
+pub struct ComponentMut<'a, T> {
+    guard: CellMut<'a, dyn Any + Send + Sync>,
+    changed_tick: &'a AtomicU32,
+    tick: u32,
+    component: PhantomData<&'a mut T>,
+}
+
+impl<'a, T: Any> ComponentMut<'a, T> {
+    /// Writes through the borrow stamp the entry with `tick`.
+    pub fn new(entry: &'a ComponentEntry, tick: u32) -> Result<Self> {
+        Ok(Self {
+            guard: entry.component.write()?,
+            changed_tick: &entry.changed_tick,
+            tick,
+            component: PhantomData,
+        })
+    }
+}
+
+impl<T: Any> Deref for ComponentMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.guard.downcast_ref::<T>().unwrap()
+    }
+}
+
+impl<T: Any> DerefMut for ComponentMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.changed_tick.store(self.tick, Ordering::Relaxed);
+        self.guard.downcast_mut::<T>().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readers_share_and_writers_are_exclusive() {
+        let cell = ComponentCell::new(1_u32);
+
+        let first = cell.read().unwrap();
+        let second = cell.read().unwrap();
+        assert!(cell.write().is_err());
+        assert_eq!(*first + *second, 2);
+        drop((first, second));
+
+        let mut writer = cell.write().unwrap();
+        assert!(cell.read().is_err());
+        assert!(cell.write().is_err());
+        *writer += 1;
+        drop(writer);
+
+        assert_eq!(*cell.read().unwrap(), 2);
+    }
+}
@@ -0,0 +1,13 @@
+/// A handle to an entity. The generation is bumped every time the slot at `index` is freed, so a
+/// handle that outlived its entity can be told apart from whatever lives in that slot now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    pub index: usize,
+    pub generation: u32,
+}
+
+impl Entity {
+    pub fn new(index: usize, generation: u32) -> Self {
+        Self { index, generation }
+    }
+}
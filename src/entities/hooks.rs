@@ -0,0 +1,102 @@
+use std::{any::Any, fmt, marker::PhantomData, sync::Arc};
+
+use crate::commands::Commands;
+
+use super::entity::Entity;
+
+pub(crate) type Hook = Arc<dyn Fn(&(dyn Any + Send + Sync), Entity, &mut Commands) + Send + Sync>;
+
+/// Callbacks run as components of type `T` come and go. `on_add` runs when an entity gets a `T`
+/// it didn't have, `on_insert` on every insertion including replacements, and `on_remove` right
+/// before a `T` is taken off an entity, whether on its own or with the whole entity.
+///
+/// Hooks only get to see the component; anything they want to change goes through `Commands`,
+/// which are applied once the call that triggered the hook is done.
+pub struct ComponentHooks<T> {
+    hooks: Hooks,
+    component: PhantomData<fn() -> T>,
+}
+
+impl<T: Any> ComponentHooks<T> {
+    pub fn new() -> Self {
+        Self {
+            hooks: Hooks::default(),
+            component: PhantomData,
+        }
+    }
+
+    pub fn on_add(
+        mut self,
+        hook: impl Fn(&T, Entity, &mut Commands) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.on_add = Some(erase(hook));
+        self
+    }
+
+    pub fn on_insert(
+        mut self,
+        hook: impl Fn(&T, Entity, &mut Commands) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.on_insert = Some(erase(hook));
+        self
+    }
+
+    pub fn on_remove(
+        mut self,
+        hook: impl Fn(&T, Entity, &mut Commands) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.on_remove = Some(erase(hook));
+        self
+    }
+
+    pub(crate) fn into_hooks(self) -> Hooks {
+        self.hooks
+    }
+}
+
+impl<T: Any> Default for ComponentHooks<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum HookKind {
+    Add,
+    Insert,
+    Remove,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct Hooks {
+    on_add: Option<Hook>,
+    on_insert: Option<Hook>,
+    on_remove: Option<Hook>,
+}
+
+impl Hooks {
+    pub(crate) fn get(&self, kind: HookKind) -> Option<&Hook> {
+        match kind {
+            HookKind::Add => self.on_add.as_ref(),
+            HookKind::Insert => self.on_insert.as_ref(),
+            HookKind::Remove => self.on_remove.as_ref(),
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("on_add", &self.on_add.is_some())
+            .field("on_insert", &self.on_insert.is_some())
+            .field("on_remove", &self.on_remove.is_some())
+            .finish()
+    }
+}
+
+fn erase<T: Any>(hook: impl Fn(&T, Entity, &mut Commands) + Send + Sync + 'static) -> Hook {
+    Arc::new(move |component, entity, commands| {
+        // Hooks are only ever looked up by the `TypeId` of `T`.
+        hook(component.downcast_ref::<T>().unwrap(), entity, commands)
+    })
+}
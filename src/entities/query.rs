@@ -1,32 +1,81 @@
-use std::any::{Any, TypeId};
-
-use crate::custom_errors::CustomErrors;
-
-use super::{query_entity::QueryEntity, Component, Entities};
+pub mod filter;
+pub mod query_data;
+
+use std::{
+    any::{Any, TypeId},
+    marker::PhantomData,
+};
+
+use crate::{custom_errors::CustomErrors, system::access::Access};
+
+use self::{
+    filter::{ChangeFilter, ChangeKind, Filter, FilterGroup},
+    query_data::QueryData,
+};
+use super::{
+    bit_set::BitSet, entity::Entity, query_entity::QueryEntity, Component, ComponentEntry, Entities,
+};
 use eyre::Result;
 
-pub type QueryIndexes = Vec<usize>;
+pub type QueryIndexes = Vec<Entity>;
 pub type QueryComponents = Vec<Vec<Component>>;
 
-pub struct Query<'a> {
-    map: u32,
+pub struct Query<'a, D: QueryData = ()> {
+    map: BitSet,
+    without: BitSet,
+    filters: Vec<Filter>,
+    change_filters: Vec<(ChangeKind, &'a [Option<ComponentEntry>])>,
+    since: Option<u32>,
     entities: &'a Entities,
     type_ids: Vec<TypeId>,
+    data: PhantomData<fn() -> D>,
 }
 
 impl<'a> Query<'a> {
     pub fn new(entities: &'a Entities) -> Self {
         Self {
             entities,
-            map: 0,
+            map: BitSet::new(),
+            without: BitSet::new(),
+            filters: vec![],
+            change_filters: vec![],
+            since: None,
             type_ids: vec![],
+            data: PhantomData,
         }
     }
+}
+
+impl<'a, D: QueryData> Query<'a, D> {
+    /// Builds a query whose required components come from `D`, failing if any of them was never
+    /// registered or if `D` borrows the same component mutably twice.
+    pub fn typed(entities: &'a Entities) -> Result<Self> {
+        D::access(&mut Access::new())?;
+
+        let mut map = BitSet::new();
+        for type_id in D::type_ids() {
+            let bit = entities
+                .get_bit(&type_id)
+                .ok_or(CustomErrors::ComponentNotRegistered)?;
+            map.insert(bit);
+        }
+
+        Ok(Self {
+            entities,
+            map,
+            without: BitSet::new(),
+            filters: vec![],
+            change_filters: vec![],
+            since: None,
+            type_ids: vec![],
+            data: PhantomData,
+        })
+    }
 
     pub fn with_component<T: Any>(&mut self) -> Result<&mut Self> {
         let type_id = TypeId::of::<T>();
-        if let Some(bit_mask) = self.entities.get_bitmask(&type_id) {
-            self.map |= bit_mask;
+        if let Some(bit) = self.entities.get_bit(&type_id) {
+            self.map.insert(bit);
             self.type_ids.push(type_id);
         } else {
             return Err(CustomErrors::ComponentNotRegistered.into());
@@ -35,20 +84,71 @@ impl<'a> Query<'a> {
         Ok(self)
     }
 
+    /// Skip every entity that holds a `T`, whatever else it has.
+    pub fn without_component<T: Any>(&mut self) -> Result<&mut Self> {
+        let type_id = TypeId::of::<T>();
+        if let Some(bit) = self.entities.get_bit(&type_id) {
+            self.without.insert(bit);
+        } else {
+            return Err(CustomErrors::ComponentNotRegistered.into());
+        }
+
+        Ok(self)
+    }
+
+    /// Keep entities matching at least one term of the group.
+    /// ```
+    /// # use ecs_in_rust::World;
+    /// # struct PlayerControlled;
+    /// # struct AiControlled;
+    /// # struct Stunned;
+    /// # let mut world = World::new();
+    /// # world.register_component::<PlayerControlled>();
+    /// # world.register_component::<AiControlled>();
+    /// # world.register_component::<Stunned>();
+    /// # world.create_entity().with_component(PlayerControlled)?;
+    /// # world.create_entity().with_component(AiControlled)?.with_component(Stunned)?;
+    /// let mut query = world.query::<()>()?;
+    /// query.any_of(|any| {
+    ///     any.with_component::<PlayerControlled>()?
+    ///         .all_of(|all| all.with_component::<AiControlled>()?.without_component::<Stunned>())
+    /// })?;
+    /// assert_eq!(query.run_entity().len(), 1);
+    /// # Ok::<(), eyre::Report>(())
+    /// ```
+    pub fn any_of(
+        &mut self,
+        build: impl for<'g> FnOnce(&'g mut FilterGroup<'a>) -> Result<&'g mut FilterGroup<'a>>,
+    ) -> Result<&mut Self> {
+        let filters = FilterGroup::build(self.entities, build)?;
+        self.filters.push(Filter::AnyOf(filters));
+        Ok(self)
+    }
+
+    /// Narrow the query with `Added<T>` or `Changed<T>`. Both also require the entity to hold a `T`.
+    pub fn filter<F: ChangeFilter>(&mut self) -> Result<&mut Self> {
+        let type_id = F::component_type_id();
+        if let Some(bit) = self.entities.get_bit(&type_id) {
+            self.map.insert(bit);
+            self.change_filters
+                .push((F::kind(), &self.entities.components[&type_id]));
+        } else {
+            return Err(CustomErrors::ComponentNotRegistered.into());
+        }
+
+        Ok(self)
+    }
+
+    /// The change tick this query last ran at; `Added` and `Changed` only match components touched
+    /// after it. Bump the world's tick with `increment_change_tick` between runs so changes made
+    /// right after a run are not lost.
+    pub fn since(&mut self, tick: u32) -> &mut Self {
+        self.since = Some(tick);
+        self
+    }
+
     pub fn run(&self) -> (QueryIndexes, QueryComponents) {
-        let indices: QueryIndexes = self
-            .entities
-            .map
-            .iter()
-            .enumerate()
-            .filter_map(|(index, entity_map)| {
-                if entity_map & self.map == self.map {
-                    Some(index)
-                } else {
-                    None
-                }
-            })
-            .collect();
+        let indices: Vec<usize> = self.matching_indices().collect();
 
         let mut result = vec![];
 
@@ -56,34 +156,79 @@ impl<'a> Query<'a> {
             let entity_components = self.entities.components.get(type_id).unwrap();
             let mut components_to_keep = vec![];
             for index in &indices {
-                components_to_keep.push(entity_components[*index].as_ref().unwrap().clone());
+                components_to_keep.push(
+                    entity_components[*index]
+                        .as_ref()
+                        .unwrap()
+                        .component
+                        .clone(),
+                );
             }
 
             result.push(components_to_keep);
         }
 
-        (indices, result)
+        let entities = indices
+            .into_iter()
+            .map(|index| self.entities.entity_at(index))
+            .collect();
+
+        (entities, result)
     }
 
-    pub fn run_entity(&self) -> Vec<QueryEntity> {
+    pub fn run_entity(&self) -> Vec<QueryEntity<'a>> {
+        self.matching_indices()
+            .map(|index| QueryEntity::new(self.entities.entity_at(index), self.entities))
+            .collect()
+    }
+
+    /// Walks every matching entity, handing out its components as described by `D`.
+    ///
+    /// # Panics
+    ///
+    /// When a component is still borrowed in a way that conflicts, like writing through a
+    /// `&mut T` query while looping over another query of `T`. Use `try_iter` to get the
+    /// `ComponentAlreadyBorrowed` error instead.
+    pub fn iter(&self) -> impl Iterator<Item = D::EntityItem<'a>> + '_ {
+        self.try_iter()
+            .map(|item| item.unwrap_or_else(|error| panic!("{error}")))
+    }
+
+    /// Like `iter`, but yields an error for every entity whose components can't be borrowed.
+    pub fn try_iter(&self) -> impl Iterator<Item = Result<D::EntityItem<'a>>> + '_ {
+        self.matching_indices().map(|index| {
+            let item = D::fetch(self.entities, index)?;
+            Ok(D::with_entity(self.entities.entity_at(index), item))
+        })
+    }
+
+    fn matching_indices(&self) -> impl Iterator<Item = usize> + '_ {
         self.entities
             .map
             .iter()
             .enumerate()
             .filter_map(|(index, entity_map)| {
-                if entity_map & self.map == self.map {
-                    Some(QueryEntity::new(index, self.entities))
+                if self.entities.alive[index]
+                    && entity_map.contains_all(&self.map)
+                    && !entity_map.intersects(&self.without)
+                    && self.filters.iter().all(|filter| filter.matches(entity_map))
+                    && self.change_filters.iter().all(|(kind, column)| {
+                        column[index]
+                            .as_ref()
+                            .is_some_and(|entry| kind.matches(entry, self.since))
+                    })
+                {
+                    Some(index)
                 } else {
                     None
                 }
             })
-            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::cell::{Ref, RefMut};
+    use crate::entities::component_ref::{ComponentMut, ComponentRef};
 
     use crate::entities::query_entity::QueryEntity;
 
@@ -99,7 +244,7 @@ mod tests {
         let mut query = Query::new(&entities);
         query.with_component::<u32>()?.with_component::<f32>()?;
 
-        assert_eq!(query.map, 3u32);
+        assert_eq!(query.map, [0, 1].into_iter().collect());
         assert_eq!(TypeId::of::<u32>(), query.type_ids[0]);
         assert_eq!(TypeId::of::<f32>(), query.type_ids[1]);
         Ok(())
@@ -136,24 +281,24 @@ mod tests {
         assert!(u32s.len() == f32s.len() && u32s.len() == indices.len());
         assert_eq!(u32s.len(), 2);
 
-        let borrowed_first_u32s = u32s[0].borrow();
+        let borrowed_first_u32s = u32s[0].read().unwrap();
         let first_u32s = borrowed_first_u32s.downcast_ref::<u32>().unwrap();
         assert_eq!(*first_u32s, 10u32);
 
-        let borrowed_first_f32s = f32s[0].borrow();
+        let borrowed_first_f32s = f32s[0].read().unwrap();
         let first_f32s = borrowed_first_f32s.downcast_ref::<f32>().unwrap();
         assert_eq!(*first_f32s, 20.0_f32);
 
-        let borrowed_sencond_u32s = u32s[1].borrow();
+        let borrowed_sencond_u32s = u32s[1].read().unwrap();
         let second_u32s = borrowed_sencond_u32s.downcast_ref::<u32>().unwrap();
         assert_eq!(*second_u32s, 15u32);
 
-        let borrowed_second_f32s = f32s[1].borrow();
+        let borrowed_second_f32s = f32s[1].read().unwrap();
         let second_f32s = borrowed_second_f32s.downcast_ref::<f32>().unwrap();
         assert_eq!(*second_f32s, 25.0_f32);
 
-        assert_eq!(indices[0], 0);
-        assert_eq!(indices[1], 3);
+        assert_eq!(indices[0].index, 0);
+        assert_eq!(indices[1].index, 3);
         Ok(())
     }
 
@@ -172,8 +317,8 @@ mod tests {
 
         assert_eq!(entities.len(), 1);
         for entity in entities {
-            assert_eq!(entity.id, 0);
-            let health: Ref<u32> = entity.get_component::<u32>()?;
+            assert_eq!(entity.id.index, 0);
+            let health: ComponentRef<u32> = entity.get_component::<u32>()?;
             assert_eq!(*health, 100);
         }
 
@@ -195,8 +340,8 @@ mod tests {
 
         assert_eq!(entities.len(), 1);
         for entity in entities {
-            assert_eq!(entity.id, 0);
-            let mut health: RefMut<u32> = entity.get_component_mut::<u32>()?;
+            assert_eq!(entity.id.index, 0);
+            let mut health: ComponentMut<u32> = entity.get_component_mut::<u32>()?;
             assert_eq!(*health, 100);
             *health += 1;
         }
@@ -205,11 +350,176 @@ mod tests {
 
         assert_eq!(entities.len(), 1);
         for entity in entities {
-            assert_eq!(entity.id, 0);
-            let health: Ref<u32> = entity.get_component::<u32>()?;
+            assert_eq!(entity.id.index, 0);
+            let health: ComponentRef<u32> = entity.get_component::<u32>()?;
             assert_eq!(*health, 101);
         }
 
         Ok(())
     }
+
+    #[test]
+    fn typed_query_yields_components() -> Result<()> {
+        let mut entities = Entities::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+
+        entities
+            .create_entity()
+            .with_component(10_u32)?
+            .with_component(1.0_f32)?;
+        entities.create_entity().with_component(20_u32)?;
+        entities
+            .create_entity()
+            .with_component(30_u32)?
+            .with_component(3.0_f32)?;
+
+        let query = Query::<(&u32, &mut f32)>::typed(&entities)?;
+        for (_entity, number, mut float) in query.iter() {
+            *float += *number as f32;
+        }
+
+        let floats: Vec<f32> = Query::<&f32>::typed(&entities)?
+            .iter()
+            .map(|(_entity, float)| *float)
+            .collect();
+        assert_eq!(floats, vec![11.0, 33.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn typed_query_requires_registered_components() {
+        let entities = Entities::default();
+        assert!(Query::<&u32>::typed(&entities).is_err());
+    }
+
+    #[test]
+    fn typed_query_rejects_aliasing_borrows() {
+        let mut entities = Entities::default();
+        entities.register_component::<u32>();
+
+        assert!(Query::<(&u32, &u32)>::typed(&entities).is_ok());
+        assert!(Query::<(&mut u32, Option<&u32>)>::typed(&entities).is_err());
+    }
+
+    #[test]
+    fn without_component_excludes_entities() -> Result<()> {
+        let mut entities = Entities::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+
+        entities
+            .create_entity()
+            .with_component(1_u32)?
+            .with_component(1.0_f32)?;
+        entities.create_entity().with_component(2_u32)?;
+        entities.create_entity().with_component(3.0_f32)?;
+
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?.without_component::<f32>()?;
+
+        let (indices, components) = query.run();
+        assert_eq!(indices.len(), 1);
+        assert_eq!(indices[0].index, 1);
+        assert_eq!(components[0].len(), 1);
+
+        let query_entities = query.run_entity();
+        assert_eq!(query_entities.len(), 1);
+        assert_eq!(query_entities[0].id.index, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn without_component_requires_registration() {
+        let entities = Entities::default();
+        let mut query = Query::new(&entities);
+        assert!(query.without_component::<u32>().is_err());
+    }
+
+    #[test]
+    fn optional_components_do_not_narrow_the_query() -> Result<()> {
+        let mut entities = Entities::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+
+        entities
+            .create_entity()
+            .with_component(1_u32)?
+            .with_component(1.5_f32)?;
+        entities.create_entity().with_component(2_u32)?;
+        entities.create_entity().with_component(3.5_f32)?;
+
+        let query = Query::<(&u32, Option<&mut f32>)>::typed(&entities)?;
+        let mut found = vec![];
+        for (_entity, number, float) in query.iter() {
+            found.push((
+                *number,
+                float.map(|mut float| {
+                    *float += 1.0;
+                    *float
+                }),
+            ));
+        }
+
+        assert_eq!(found, vec![(1, Some(2.5)), (2, None)]);
+        Ok(())
+    }
+
+    #[test]
+    fn any_of_matches_either_component() -> Result<()> {
+        let mut entities = Entities::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+        entities.register_component::<bool>();
+
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2.0_f32)?;
+        entities.create_entity().with_component(true)?;
+        entities
+            .create_entity()
+            .with_component(4_u32)?
+            .with_component(false)?;
+
+        let mut query = Query::new(&entities);
+        query
+            .without_component::<bool>()?
+            .any_of(|any| any.with_component::<u32>()?.with_component::<f32>())?;
+
+        let indices: Vec<usize> = query.run().0.iter().map(|entity| entity.index).collect();
+        assert_eq!(indices, vec![0, 1]);
+        assert_eq!(query.run_entity().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn changed_filter_only_sees_mutated_components() -> Result<()> {
+        use super::filter::{Added, Changed};
+
+        let mut entities = Entities::default();
+        entities.register_component::<u32>();
+
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2_u32)?;
+
+        let last_run = entities.change_tick();
+        entities.increment_change_tick();
+
+        for entity in Query::new(&entities).with_component::<u32>()?.run_entity() {
+            if entity.id.index == 1 {
+                *entity.get_component_mut::<u32>()? += 1;
+            }
+        }
+
+        let mut changed = Query::<&u32>::typed(&entities)?;
+        changed.filter::<Changed<u32>>()?.since(last_run);
+        let values: Vec<u32> = changed.iter().map(|(_entity, value)| *value).collect();
+        assert_eq!(values, vec![3]);
+
+        let mut added = Query::new(&entities);
+        added.filter::<Added<u32>>()?;
+        assert_eq!(added.run_entity().len(), 2);
+        added.since(last_run);
+        assert!(added.run_entity().is_empty());
+        Ok(())
+    }
 }
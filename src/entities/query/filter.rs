@@ -0,0 +1,192 @@
+use std::{
+    any::{Any, TypeId},
+    marker::PhantomData,
+};
+
+use eyre::Result;
+
+use crate::{
+    custom_errors::CustomErrors,
+    entities::{bit_set::BitSet, ComponentEntry, Entities},
+};
+
+/// A single term of a query's filter tree, evaluated against an entity's map.
+#[derive(Debug, Clone)]
+pub enum Filter {
+    With(usize),
+    Without(usize),
+    AnyOf(Vec<Filter>),
+    AllOf(Vec<Filter>),
+}
+
+impl Filter {
+    /// An empty `AnyOf` never matches and an empty `AllOf` always does.
+    pub fn matches(&self, entity_map: &BitSet) -> bool {
+        match self {
+            Filter::With(bit) => entity_map.contains(*bit),
+            Filter::Without(bit) => !entity_map.contains(*bit),
+            Filter::AnyOf(filters) => filters.iter().any(|filter| filter.matches(entity_map)),
+            Filter::AllOf(filters) => filters.iter().all(|filter| filter.matches(entity_map)),
+        }
+    }
+}
+
+/// Matches entities whose `T` was inserted after the query's `since` tick.
+pub struct Added<T>(PhantomData<T>);
+
+/// Matches entities whose `T` was inserted, or written through a `ComponentMut`, after the query's
+/// `since` tick.
+pub struct Changed<T>(PhantomData<T>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Changed,
+}
+
+pub trait ChangeFilter {
+    fn component_type_id() -> TypeId;
+    fn kind() -> ChangeKind;
+}
+
+impl<T: Any> ChangeFilter for Added<T> {
+    fn component_type_id() -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn kind() -> ChangeKind {
+        ChangeKind::Added
+    }
+}
+
+impl<T: Any> ChangeFilter for Changed<T> {
+    fn component_type_id() -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn kind() -> ChangeKind {
+        ChangeKind::Changed
+    }
+}
+
+impl ChangeKind {
+    /// A query that never ran has no `since` tick, and sees every component as new.
+    pub fn matches(&self, entry: &ComponentEntry, since: Option<u32>) -> bool {
+        let tick = match self {
+            ChangeKind::Added => entry.added_tick,
+            ChangeKind::Changed => entry.changed_tick(),
+        };
+        since.is_none_or(|since| tick > since)
+    }
+}
+
+/// Collects the terms of an `any_of` or `all_of` group. Groups nest, so
+/// `(A or B) and not C` can be written out directly.
+pub struct FilterGroup<'a> {
+    entities: &'a Entities,
+    filters: Vec<Filter>,
+}
+
+impl<'a> FilterGroup<'a> {
+    pub fn new(entities: &'a Entities) -> Self {
+        Self {
+            entities,
+            filters: vec![],
+        }
+    }
+
+    pub fn with_component<T: Any>(&mut self) -> Result<&mut Self> {
+        let bit = self.bit_of::<T>()?;
+        self.filters.push(Filter::With(bit));
+        Ok(self)
+    }
+
+    pub fn without_component<T: Any>(&mut self) -> Result<&mut Self> {
+        let bit = self.bit_of::<T>()?;
+        self.filters.push(Filter::Without(bit));
+        Ok(self)
+    }
+
+    pub fn any_of(
+        &mut self,
+        build: impl for<'g> FnOnce(&'g mut FilterGroup<'a>) -> Result<&'g mut FilterGroup<'a>>,
+    ) -> Result<&mut Self> {
+        let filters = FilterGroup::build(self.entities, build)?;
+        self.filters.push(Filter::AnyOf(filters));
+        Ok(self)
+    }
+
+    pub fn all_of(
+        &mut self,
+        build: impl for<'g> FnOnce(&'g mut FilterGroup<'a>) -> Result<&'g mut FilterGroup<'a>>,
+    ) -> Result<&mut Self> {
+        let filters = FilterGroup::build(self.entities, build)?;
+        self.filters.push(Filter::AllOf(filters));
+        Ok(self)
+    }
+
+    pub(crate) fn build(
+        entities: &'a Entities,
+        build: impl for<'g> FnOnce(&'g mut FilterGroup<'a>) -> Result<&'g mut FilterGroup<'a>>,
+    ) -> Result<Vec<Filter>> {
+        let mut group = FilterGroup::new(entities);
+        build(&mut group)?;
+        Ok(group.filters)
+    }
+
+    fn bit_of<T: Any>(&self) -> Result<usize> {
+        self.entities
+            .get_bit(&TypeId::of::<T>())
+            .ok_or(CustomErrors::ComponentNotRegistered.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_groups() {
+        let bits = |bits: &[usize]| bits.iter().copied().collect::<BitSet>();
+        let filter = Filter::AllOf(vec![
+            Filter::AnyOf(vec![Filter::With(0), Filter::With(1)]),
+            Filter::Without(2),
+        ]);
+
+        assert!(filter.matches(&bits(&[0])));
+        assert!(filter.matches(&bits(&[1, 3])));
+        assert!(!filter.matches(&bits(&[0, 2])));
+        assert!(!filter.matches(&bits(&[3])));
+    }
+
+    #[test]
+    fn change_kinds_compare_against_since() {
+        let entry = ComponentEntry::new(
+            std::sync::Arc::new(crate::entities::component_ref::ComponentCell::new(1_u32)),
+            2,
+        );
+        entry.mark_changed(5);
+
+        assert!(ChangeKind::Added.matches(&entry, None));
+        assert!(ChangeKind::Added.matches(&entry, Some(1)));
+        assert!(!ChangeKind::Added.matches(&entry, Some(2)));
+        assert!(ChangeKind::Changed.matches(&entry, Some(4)));
+        assert!(!ChangeKind::Changed.matches(&entry, Some(5)));
+    }
+
+    #[test]
+    fn empty_groups() {
+        assert!(!Filter::AnyOf(vec![]).matches(&BitSet::new()));
+        assert!(Filter::AllOf(vec![]).matches(&BitSet::new()));
+    }
+
+    #[test]
+    fn unregistered_component_in_group() {
+        let entities = Entities::default();
+        let mut group = FilterGroup::new(&entities);
+        assert!(group.with_component::<u32>().is_err());
+        assert!(group
+            .any_of(|group| group.without_component::<u32>())
+            .is_err());
+    }
+}
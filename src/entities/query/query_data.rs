@@ -0,0 +1,192 @@
+use std::any::{type_name, Any, TypeId};
+
+use eyre::Result;
+
+use crate::{
+    entities::{
+        component_ref::{ComponentMut, ComponentRef},
+        entity::Entity,
+        Entities,
+    },
+    system::access::Access,
+};
+
+/// What a typed query hands back for every matching entity. Implemented for `&T`, `&mut T` and
+/// tuples of those, so `world.query::<(&Location, &mut Size)>()` yields `ComponentRef<Location>`
+/// and `ComponentMut<Size>` without any downcasting on the caller's side. Writing through a
+/// `ComponentMut` marks the component as changed, merely fetching it doesn't.
+pub trait QueryData {
+    type Item<'a>;
+    /// `Item` with the entity's handle in front, flattened so tuples iterate as
+    /// `(Entity, A, B, ..)`.
+    type EntityItem<'a>;
+
+    /// Components an entity needs to have for `fetch` to succeed.
+    fn type_ids() -> Vec<TypeId>;
+
+    /// Records which components get borrowed and how, failing when they'd overlap.
+    fn access(access: &mut Access) -> Result<()>;
+
+    /// Only called for entities whose map contains every bit of `type_ids`. Fails when one of the
+    /// components is already borrowed in a way that conflicts.
+    fn fetch(entities: &Entities, index: usize) -> Result<Self::Item<'_>>;
+
+    fn with_entity(entity: Entity, item: Self::Item<'_>) -> Self::EntityItem<'_>;
+}
+
+impl QueryData for () {
+    type Item<'a> = ();
+    type EntityItem<'a> = Entity;
+
+    fn type_ids() -> Vec<TypeId> {
+        vec![]
+    }
+
+    fn access(_access: &mut Access) -> Result<()> {
+        Ok(())
+    }
+
+    fn fetch(_entities: &Entities, _index: usize) -> Result<Self::Item<'_>> {
+        Ok(())
+    }
+
+    fn with_entity(entity: Entity, _item: Self::Item<'_>) -> Self::EntityItem<'_> {
+        entity
+    }
+}
+
+impl<T: Any> QueryData for &T {
+    type Item<'a> = ComponentRef<'a, T>;
+    type EntityItem<'a> = (Entity, ComponentRef<'a, T>);
+
+    fn type_ids() -> Vec<TypeId> {
+        vec![TypeId::of::<T>()]
+    }
+
+    fn access(access: &mut Access) -> Result<()> {
+        access.add_component_read(TypeId::of::<T>(), type_name::<T>())
+    }
+
+    fn fetch(entities: &Entities, index: usize) -> Result<Self::Item<'_>> {
+        let entry = entities.components[&TypeId::of::<T>()][index]
+            .as_ref()
+            .unwrap();
+        ComponentRef::new(&entry.component)
+    }
+
+    fn with_entity(entity: Entity, item: Self::Item<'_>) -> Self::EntityItem<'_> {
+        (entity, item)
+    }
+}
+
+impl<T: Any> QueryData for &mut T {
+    type Item<'a> = ComponentMut<'a, T>;
+    type EntityItem<'a> = (Entity, ComponentMut<'a, T>);
+
+    fn type_ids() -> Vec<TypeId> {
+        vec![TypeId::of::<T>()]
+    }
+
+    fn access(access: &mut Access) -> Result<()> {
+        access.add_component_write(TypeId::of::<T>(), type_name::<T>())
+    }
+
+    fn fetch(entities: &Entities, index: usize) -> Result<Self::Item<'_>> {
+        let entry = entities.components[&TypeId::of::<T>()][index]
+            .as_ref()
+            .unwrap();
+        ComponentMut::new(entry, entities.change_tick)
+    }
+
+    fn with_entity(entity: Entity, item: Self::Item<'_>) -> Self::EntityItem<'_> {
+        (entity, item)
+    }
+}
+
+/// Doesn't narrow the query; yields `None` for entities without a `T`.
+impl<T: Any> QueryData for Option<&T> {
+    type Item<'a> = Option<ComponentRef<'a, T>>;
+    type EntityItem<'a> = (Entity, Option<ComponentRef<'a, T>>);
+
+    fn type_ids() -> Vec<TypeId> {
+        vec![]
+    }
+
+    fn access(access: &mut Access) -> Result<()> {
+        access.add_component_read(TypeId::of::<T>(), type_name::<T>())
+    }
+
+    fn fetch(entities: &Entities, index: usize) -> Result<Self::Item<'_>> {
+        match entities.component(&TypeId::of::<T>(), index) {
+            Some(entry) => Ok(Some(ComponentRef::new(&entry.component)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn with_entity(entity: Entity, item: Self::Item<'_>) -> Self::EntityItem<'_> {
+        (entity, item)
+    }
+}
+
+impl<T: Any> QueryData for Option<&mut T> {
+    type Item<'a> = Option<ComponentMut<'a, T>>;
+    type EntityItem<'a> = (Entity, Option<ComponentMut<'a, T>>);
+
+    fn type_ids() -> Vec<TypeId> {
+        vec![]
+    }
+
+    fn access(access: &mut Access) -> Result<()> {
+        access.add_component_write(TypeId::of::<T>(), type_name::<T>())
+    }
+
+    fn fetch(entities: &Entities, index: usize) -> Result<Self::Item<'_>> {
+        match entities.component(&TypeId::of::<T>(), index) {
+            Some(entry) => Ok(Some(ComponentMut::new(entry, entities.change_tick)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn with_entity(entity: Entity, item: Self::Item<'_>) -> Self::EntityItem<'_> {
+        (entity, item)
+    }
+}
+
+macro_rules! impl_query_data_for_tuple {
+    ($($data:ident),+) => {
+        impl<$($data: QueryData),+> QueryData for ($($data,)+) {
+            type Item<'a> = ($($data::Item<'a>,)+);
+            type EntityItem<'a> = (Entity, $($data::Item<'a>,)+);
+
+            fn type_ids() -> Vec<TypeId> {
+                let mut type_ids = vec![];
+                $(type_ids.extend($data::type_ids());)+
+                type_ids
+            }
+
+            fn access(access: &mut Access) -> Result<()> {
+                $($data::access(access)?;)+
+                Ok(())
+            }
+
+            fn fetch(entities: &Entities, index: usize) -> Result<Self::Item<'_>> {
+                Ok(($($data::fetch(entities, index)?,)+))
+            }
+
+            #[allow(non_snake_case)]
+            fn with_entity(entity: Entity, item: Self::Item<'_>) -> Self::EntityItem<'_> {
+                let ($($data,)+) = item;
+                (entity, $($data,)+)
+            }
+        }
+    };
+}
+
+impl_query_data_for_tuple!(A);
+impl_query_data_for_tuple!(A, B);
+impl_query_data_for_tuple!(A, B, C);
+impl_query_data_for_tuple!(A, B, C, D);
+impl_query_data_for_tuple!(A, B, C, D, E);
+impl_query_data_for_tuple!(A, B, C, D, E, F);
+impl_query_data_for_tuple!(A, B, C, D, E, F, G);
+impl_query_data_for_tuple!(A, B, C, D, E, F, G, H);
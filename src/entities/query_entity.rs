@@ -1,54 +1,49 @@
 use eyre::Result;
-use std::{
-    any::{Any, TypeId},
-    cell::{Ref, RefCell, RefMut},
-    rc::Rc,
-};
+use std::any::{Any, TypeId};
 
 use crate::custom_errors::CustomErrors;
 
-use super::Entities;
+use super::{
+    component_ref::{ComponentMut, ComponentRef},
+    entity::Entity,
+    ComponentEntry, Entities,
+};
 pub struct QueryEntity<'a> {
-    pub id: usize,
+    pub id: Entity,
     entities: &'a Entities,
 }
 
 impl<'a> QueryEntity<'a> {
-    pub fn new(id: usize, entities: &'a Entities) -> Self {
+    pub fn new(id: Entity, entities: &'a Entities) -> Self {
         Self { id, entities }
     }
 
-    fn extract_components<T: Any>(&self) -> Result<&Vec<Option<Rc<RefCell<dyn Any>>>>> {
+    fn extract_components<T: Any>(&self) -> Result<&[Option<ComponentEntry>]> {
         let type_id = TypeId::of::<T>();
         self.entities
             .components
             .get(&type_id)
+            .map(Vec::as_slice)
             .ok_or(CustomErrors::ComponentNotRegistered.into())
     }
 
-    pub fn get_component<T: Any>(&self) -> Result<Ref<T>> {
+    pub fn get_component<T: Any>(&self) -> Result<ComponentRef<'_, T>> {
         let components = self.extract_components::<T>().unwrap();
 
-        let borrowed_component = components[self.id]
+        let entry = components[self.id.index]
             .as_ref()
-            .ok_or(CustomErrors::ComponentDoesNotExists)?
-            .borrow();
+            .ok_or(CustomErrors::ComponentDoesNotExists)?;
 
-        Ok(Ref::map(borrowed_component, |any| {
-            any.downcast_ref::<T>().unwrap()
-        }))
+        ComponentRef::new(&entry.component)
     }
 
-    pub fn get_component_mut<T: Any>(&self) -> Result<RefMut<T>> {
+    pub fn get_component_mut<T: Any>(&self) -> Result<ComponentMut<'_, T>> {
         let components = self.extract_components::<T>().unwrap();
 
-        let borrowed_component = components[self.id]
+        let entry = components[self.id.index]
             .as_ref()
-            .ok_or(CustomErrors::ComponentDoesNotExists)?
-            .borrow_mut();
+            .ok_or(CustomErrors::ComponentDoesNotExists)?;
 
-        Ok(RefMut::map(borrowed_component, |any| {
-            any.downcast_mut::<T>().unwrap()
-        }))
+        ComponentMut::new(entry, self.entities.change_tick)
     }
 }
@@ -0,0 +1,120 @@
+use std::{any::Any, any::TypeId, marker::PhantomData};
+
+use crate::World;
+
+use super::entity::Entity;
+
+/// Every removal of one component type, in order. `start` is the position of `entities[0]` in
+/// the full history, which lets readers keep absolute cursors while old entries get cleared.
+/// `updated_at` is where the log ended at the last `update`.
+#[derive(Debug, Default)]
+pub struct RemovedLog {
+    entities: Vec<Entity>,
+    start: usize,
+    updated_at: usize,
+}
+
+impl RemovedLog {
+    pub fn push(&mut self, entity: Entity) {
+        self.entities.push(entity);
+    }
+
+    pub fn clear(&mut self) {
+        self.start += self.entities.len();
+        self.entities.clear();
+    }
+
+    /// Drops the removals that were already there at the previous update, so each one lives
+    /// through two updates, like the two buffers of `Events`.
+    pub fn update(&mut self) {
+        let stale = self
+            .updated_at
+            .saturating_sub(self.start)
+            .min(self.entities.len());
+        self.entities.drain(..stale);
+        self.start += stale;
+        self.updated_at = self.end();
+    }
+
+    fn end(&self) -> usize {
+        self.start + self.entities.len()
+    }
+
+    fn since(&self, cursor: usize) -> &[Entity] {
+        &self.entities[cursor.saturating_sub(self.start).min(self.entities.len())..]
+    }
+}
+
+/// Reads the entities that lost a `T`, either through `delete_component_by_entity_id` or
+/// because the whole entity was deleted. Each reader keeps its own cursor, so every reader sees
+/// each removal once.
+///
+/// `Schedule::run` drops removals once they are two runs old, so a reader has to read at least
+/// once per run to see all of them. Without a schedule, call `World::update_removed_components`
+/// or `World::clear_removed_components` every frame to keep the logs from growing.
+#[derive(Debug)]
+pub struct RemovedComponents<T> {
+    cursor: usize,
+    component: PhantomData<fn() -> T>,
+}
+
+impl<T: Any> Default for RemovedComponents<T> {
+    fn default() -> Self {
+        Self {
+            cursor: 0,
+            component: PhantomData,
+        }
+    }
+}
+
+impl<T: Any> RemovedComponents<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removals since the last `read`. Entries dropped by `World::clear_removed_components`
+    /// before this reader got to them are gone for good.
+    pub fn read(&mut self, world: &World) -> Vec<Entity> {
+        let Some(log) = world.entities.removed.get(&TypeId::of::<T>()) else {
+            return vec![];
+        };
+
+        let removed = log.since(self.cursor).to_vec();
+        self.cursor = log.end();
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_keeps_absolute_positions_across_clears() {
+        let mut log = RemovedLog::default();
+        log.push(Entity::new(0, 0));
+        log.push(Entity::new(1, 0));
+        assert_eq!(log.since(1), &[Entity::new(1, 0)]);
+
+        log.clear();
+        log.push(Entity::new(2, 0));
+        assert_eq!(log.since(0), &[Entity::new(2, 0)]);
+        assert_eq!(log.since(3), &[]);
+        assert_eq!(log.end(), 3);
+    }
+
+    #[test]
+    fn updates_keep_the_last_two_rounds() {
+        let mut log = RemovedLog::default();
+        log.push(Entity::new(0, 0));
+        log.update();
+        log.push(Entity::new(1, 0));
+        assert_eq!(log.since(0), &[Entity::new(0, 0), Entity::new(1, 0)]);
+
+        log.update();
+        assert_eq!(log.since(0), &[Entity::new(1, 0)]);
+        log.update();
+        assert_eq!(log.since(0), &[]);
+        assert_eq!(log.end(), 2);
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    marker::PhantomData,
+};
+
+use eyre::Result;
+
+use crate::{
+    system::{
+        access::Access,
+        system_param::{SystemParam, SystemView},
+    },
+    World,
+};
+
+/// A double-buffered channel of `T` events, kept as a resource by `World::add_event`. Events
+/// are sent into the current buffer; every `update` drops the previous buffer and makes the
+/// current one the previous, so an event lives through two updates before it is gone.
+#[derive(Debug)]
+pub struct Events<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    start: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            previous: vec![],
+            current: vec![],
+            start: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    pub fn update(&mut self) {
+        self.start += self.previous.len();
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+
+    fn end(&self) -> usize {
+        self.start + self.previous.len() + self.current.len()
+    }
+
+    fn since(&self, cursor: usize) -> impl Iterator<Item = &T> {
+        self.previous
+            .iter()
+            .chain(self.current.iter())
+            .skip(cursor.saturating_sub(self.start))
+    }
+}
+
+/// A reader's position in an `Events<T>`. Every cursor sees each event once, as long as it reads
+/// before the event is dropped.
+#[derive(Debug)]
+pub struct EventCursor<T> {
+    cursor: usize,
+    event: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventCursor<T> {
+    fn default() -> Self {
+        Self {
+            cursor: 0,
+            event: PhantomData,
+        }
+    }
+}
+
+impl<T> EventCursor<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events sent since the last `read`.
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        let unread = events.since(self.cursor);
+        self.cursor = events.end();
+        unread
+    }
+
+    pub fn has_unread(&self, events: &Events<T>) -> bool {
+        events.end() > self.cursor.max(events.start)
+    }
+}
+
+/// Sends `T` events, `EventWriter<Collision>` in a system's signature.
+pub struct EventWriter<'w, T> {
+    events: &'w mut Events<T>,
+}
+
+impl<T> EventWriter<'_, T> {
+    pub fn send(&mut self, event: T) {
+        self.events.send(event);
+    }
+}
+
+impl<T: Any + Send + Sync> SystemParam for EventWriter<'_, T> {
+    type State = ();
+    type Item<'w, 's> = EventWriter<'w, T>;
+
+    fn access(access: &mut Access) -> Result<()> {
+        access.add_resource_write(TypeId::of::<Events<T>>(), type_name::<Events<T>>())
+    }
+
+    fn fetch<'w, 's>(
+        _state: &'s mut Self::State,
+        view: &mut SystemView<'w>,
+    ) -> Result<Self::Item<'w, 's>> {
+        Ok(EventWriter {
+            events: view.resource_mut::<Events<T>>()?,
+        })
+    }
+}
+
+/// Reads `T` events, `EventReader<Collision>` in a system's signature. The cursor belongs to the
+/// system, so each system reading the same events sees all of them.
+pub struct EventReader<'w, 's, T> {
+    cursor: &'s mut EventCursor<T>,
+    events: &'w Events<T>,
+}
+
+impl<'w, T> EventReader<'w, '_, T> {
+    pub fn read(&mut self) -> impl Iterator<Item = &'w T> {
+        self.cursor.read(self.events)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.cursor.has_unread(self.events)
+    }
+}
+
+impl<T: Any + Send + Sync> SystemParam for EventReader<'_, '_, T> {
+    type State = EventCursor<T>;
+    type Item<'w, 's> = EventReader<'w, 's, T>;
+
+    fn access(access: &mut Access) -> Result<()> {
+        access.add_resource_read(TypeId::of::<Events<T>>(), type_name::<Events<T>>())
+    }
+
+    fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        view: &mut SystemView<'w>,
+    ) -> Result<Self::Item<'w, 's>> {
+        Ok(EventReader {
+            cursor: state,
+            events: view.resource::<Events<T>>()?,
+        })
+    }
+}
+
+pub(crate) fn update_events<T: Any + Send + Sync>(world: &mut World) {
+    if let Some(events) = world.get_resource_mut::<Events<T>>() {
+        events.update();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_dropped_after_two_updates() {
+        let mut events = Events::default();
+        let mut early = EventCursor::new();
+        let mut late = EventCursor::new();
+
+        events.send(1);
+        assert_eq!(early.read(&events).collect::<Vec<_>>(), vec![&1]);
+
+        events.update();
+        events.send(2);
+        assert!(late.has_unread(&events));
+        assert_eq!(early.read(&events).collect::<Vec<_>>(), vec![&2]);
+        assert!(!early.has_unread(&events));
+
+        events.update();
+        events.send(3);
+        assert_eq!(late.read(&events).collect::<Vec<_>>(), vec![&2, &3]);
+
+        events.update();
+        events.update();
+        assert!(events.is_empty());
+        assert!(!early.has_unread(&events));
+        assert_eq!(early.read(&events).count(), 0);
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    any::{Any, TypeId},
+    collections::VecDeque,
+    ops::Deref,
+};
+
+use eyre::Result;
+
+use crate::{
+    custom_errors::CustomErrors,
+    entities::{component_ref::ComponentRef, entity::Entity, hooks::ComponentHooks},
+    World,
+};
+
+/// The entity this one belongs to. Only set through `World::add_child`, which keeps it in step
+/// with the parent's `Children`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub(crate) Entity);
+
+impl Parent {
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+/// The entities belonging to this one, in the order they were added.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(pub(crate) Vec<Entity>);
+
+impl Deref for Children {
+    type Target = [Entity];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Also installs the hooks that keep both sides in step when one of them is removed directly,
+/// like through `delete_component_by_entity_id` or `Commands::remove`.
+fn ensure_registered(world: &mut World) {
+    if !world.entities.has_hooks(&TypeId::of::<Parent>()) {
+        world.register_component_with_hooks(ComponentHooks::<Parent>::new().on_remove(
+            |parent, child, commands| {
+                let parent = parent.0;
+                commands.add(child, move |world| forget_child(world, parent, child));
+            },
+        ));
+    }
+    if !world.entities.has_hooks(&TypeId::of::<Children>()) {
+        world.register_component_with_hooks(ComponentHooks::<Children>::new().on_remove(
+            |children, parent, commands| {
+                let children = children.0.clone();
+                commands.add(parent, move |world| orphan(world, parent, children));
+            },
+        ));
+    }
+}
+
+/// Drops `child` from the `Children` of its former parent, unless it got it back in the meantime.
+fn forget_child(world: &mut World, parent: Entity, child: Entity) -> Result<()> {
+    if !world.is_alive(parent)
+        || (world.is_alive(child) && parent_of(world, child)? == Some(parent))
+    {
+        return Ok(());
+    }
+
+    let mut children = children_of(world, parent)?;
+    if children.contains(&child) {
+        children.retain(|sibling| *sibling != child);
+        set_children(world, parent, children)?;
+    }
+    Ok(())
+}
+
+/// Takes `Parent` off the former children of `parent` that it no longer lists.
+fn orphan(world: &mut World, parent: Entity, children: Vec<Entity>) -> Result<()> {
+    let kept = if world.is_alive(parent) {
+        children_of(world, parent)?
+    } else {
+        vec![]
+    };
+    for child in children {
+        if world.is_alive(child)
+            && !kept.contains(&child)
+            && parent_of(world, child)? == Some(parent)
+        {
+            world
+                .entities
+                .delete_component_by_entity_id::<Parent>(child)?;
+        }
+    }
+    Ok(())
+}
+
+fn read<T: Any + Clone>(world: &World, entity: Entity) -> Result<Option<T>> {
+    let Some(entry) = world.entities.component(&TypeId::of::<T>(), entity.index) else {
+        return Ok(None);
+    };
+    Ok(Some(ComponentRef::<T>::new(&entry.component)?.clone()))
+}
+
+pub(crate) fn parent_of(world: &World, entity: Entity) -> Result<Option<Entity>> {
+    world.entities.index_of(entity)?;
+    Ok(read::<Parent>(world, entity)?.map(|parent| parent.0))
+}
+
+pub(crate) fn children_of(world: &World, entity: Entity) -> Result<Vec<Entity>> {
+    world.entities.index_of(entity)?;
+    Ok(read::<Children>(world, entity)?.unwrap_or_default().0)
+}
+
+fn set_children(world: &mut World, parent: Entity, children: Vec<Entity>) -> Result<()> {
+    if children.is_empty() {
+        world
+            .entities
+            .delete_component_by_entity_id::<Children>(parent)
+    } else {
+        world
+            .entities
+            .add_component_by_entity_id(Children(children), parent)
+    }
+}
+
+/// Makes `child` a child of `parent`, taking it away from the parent it had before.
+pub(crate) fn add_child(world: &mut World, parent: Entity, child: Entity) -> Result<()> {
+    ensure_registered(world);
+    world.entities.index_of(parent)?;
+    let old_parent = parent_of(world, child)?;
+    if old_parent == Some(parent) {
+        return Ok(());
+    }
+
+    let mut ancestor = Some(parent);
+    while let Some(entity) = ancestor {
+        if entity == child {
+            return Err(CustomErrors::HierarchyCycle.into());
+        }
+        ancestor = parent_of(world, entity)?;
+    }
+
+    if let Some(old_parent) = old_parent {
+        let mut siblings = children_of(world, old_parent)?;
+        siblings.retain(|sibling| *sibling != child);
+        set_children(world, old_parent, siblings)?;
+    }
+
+    world
+        .entities
+        .add_component_by_entity_id(Parent(parent), child)?;
+    let mut children = children_of(world, parent)?;
+    children.push(child);
+    set_children(world, parent, children)
+}
+
+pub(crate) fn remove_child(world: &mut World, parent: Entity, child: Entity) -> Result<()> {
+    if parent_of(world, child)? != Some(parent) {
+        return Err(CustomErrors::NotAChild.into());
+    }
+
+    world
+        .entities
+        .delete_component_by_entity_id::<Parent>(child)?;
+    let mut children = children_of(world, parent)?;
+    children.retain(|sibling| *sibling != child);
+    set_children(world, parent, children)
+}
+
+/// Cuts `entity` loose from its parent and its children, ahead of deleting it.
+pub(crate) fn detach(world: &mut World, entity: Entity) -> Result<()> {
+    if let Some(parent) = parent_of(world, entity)? {
+        remove_child(world, parent, entity)?;
+    }
+    for child in children_of(world, entity)? {
+        remove_child(world, entity, child)?;
+    }
+    Ok(())
+}
+
+/// Deletes the deepest descendants first, so every entity still has its parent while it is
+/// being taken out of the hierarchy. Anything the deletions already took with them is skipped.
+pub(crate) fn despawn_recursive(world: &mut World, entity: Entity) -> Result<()> {
+    let subtree: Vec<Entity> = Descendants::depth_first(world, entity)?.collect();
+    for descendant in subtree.into_iter().rev() {
+        if world.is_alive(descendant) {
+            world.delete_entity_by_id(descendant)?;
+        }
+    }
+    if world.is_alive(entity) {
+        world.delete_entity_by_id(entity)?;
+    }
+    Ok(())
+}
+
+/// Walks every descendant of an entity, not including the entity itself. Children that were
+/// already deleted are skipped.
+///
+/// # Panics
+///
+/// When the `Children` of an entity on the way is mutably borrowed.
+pub struct Descendants<'w> {
+    world: &'w World,
+    pending: VecDeque<Entity>,
+    depth_first: bool,
+}
+
+impl<'w> Descendants<'w> {
+    /// Children come right after their parent, before the parent's next sibling.
+    pub fn depth_first(world: &'w World, entity: Entity) -> Result<Self> {
+        let mut pending: VecDeque<Entity> = children_of(world, entity)?.into();
+        pending.make_contiguous().reverse();
+        Ok(Self {
+            world,
+            pending,
+            depth_first: true,
+        })
+    }
+
+    /// Every child before any grandchild, and so on down.
+    pub fn breadth_first(world: &'w World, entity: Entity) -> Result<Self> {
+        Ok(Self {
+            world,
+            pending: children_of(world, entity)?.into(),
+            depth_first: false,
+        })
+    }
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entity = loop {
+            let entity = if self.depth_first {
+                self.pending.pop_back()?
+            } else {
+                self.pending.pop_front()?
+            };
+            if self.world.is_alive(entity) {
+                break entity;
+            }
+        };
+
+        let children = children_of(self.world, entity).unwrap_or_else(|error| panic!("{error}"));
+        if self.depth_first {
+            self.pending.extend(children.into_iter().rev());
+        } else {
+            self.pending.extend(children);
+        }
+        Some(entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reparenting_keeps_both_sides_in_step() -> Result<()> {
+        let mut world = World::new();
+        let first = world.create_entity().entity();
+        let second = world.create_entity().entity();
+        let child = world.create_entity().entity();
+
+        add_child(&mut world, first, child)?;
+        add_child(&mut world, second, child)?;
+
+        assert_eq!(parent_of(&world, child)?, Some(second));
+        assert!(children_of(&world, first)?.is_empty());
+        assert_eq!(read::<Children>(&world, first)?, None);
+        assert_eq!(children_of(&world, second)?, vec![child]);
+
+        assert!(add_child(&mut world, child, second).is_err());
+        assert!(add_child(&mut world, child, child).is_err());
+        assert!(remove_child(&mut world, first, child).is_err());
+
+        remove_child(&mut world, second, child)?;
+        assert_eq!(parent_of(&world, child)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn descendants_skip_deleted_children() -> Result<()> {
+        let mut world = World::new();
+        let root = world.create_entity().entity();
+        let gone = world.create_entity().entity();
+        let kept = world.create_entity().entity();
+        add_child(&mut world, root, gone)?;
+        add_child(&mut world, root, kept)?;
+
+        // Behind the hooks' back, before their commands get applied.
+        world
+            .entities
+            .delete_component_by_entity_id::<Parent>(gone)?;
+        world.entities.delete_entity_by_id(gone)?;
+
+        assert_eq!(children_of(&world, root)?, vec![gone, kept]);
+        assert_eq!(
+            Descendants::depth_first(&world, root)?.collect::<Vec<_>>(),
+            vec![kept]
+        );
+        assert_eq!(
+            Descendants::breadth_first(&world, root)?.collect::<Vec<_>>(),
+            vec![kept]
+        );
+        Ok(())
+    }
+}
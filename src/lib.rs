@@ -1,17 +1,69 @@
 use std::any::Any;
 
-use entities::{query::Query, Entities};
+use custom_errors::CustomErrors;
+use entities::Entities;
 use eyre::Result;
 use resource::Resource;
 
+pub use commands::{CommandError, CommandQueue, Commands};
+pub use events::{EventCursor, EventReader, EventWriter, Events};
+pub use hierarchy::{Children, Descendants, Parent};
+pub use observer::{EntityEvent, Trigger};
+pub use relation::{OnDelete, Related, Relation};
+pub use rollback::Rollback;
+pub use scene::{
+    value::{FromSceneValue, Position, Value, ValueKind},
+    SceneRegistry,
+};
+pub use schedule::{
+    condition::{every_n_ticks, on_event, resource_equals, resource_exists, SystemSet},
+    config::{IntoSystemConfig, SystemConfig},
+    executor::ExecutorKind,
+    Schedule,
+};
+pub use snapshot::{
+    binary::{Binary, Reader},
+    SerializerRegistry, SNAPSHOT_VERSION,
+};
+pub use system::{
+    system_param::{Res, ResMut, SystemParam},
+    IntoSystem, System, SystemOutput,
+};
+
+pub use entities::{
+    bundle::{Bundle, BundleWriter},
+    entity::Entity,
+    hooks::ComponentHooks,
+    query::{
+        filter::{Added, Changed},
+        query_data::QueryData,
+        Query,
+    },
+    removed_components::RemovedComponents,
+};
+
+mod commands;
 pub mod custom_errors;
 mod entities;
+mod events;
+mod hierarchy;
+mod observer;
+mod relation;
 mod resource;
+mod rollback;
+mod scene;
+mod schedule;
+mod snapshot;
+mod system;
 
 #[derive(Default)]
 pub struct World {
     resources: Resource,
     entities: Entities,
+    event_updaters: Vec<fn(&mut World)>,
+    rollback: Rollback,
+    relations: relation::Relations,
+    observers: observer::Observers,
 }
 
 impl World {
@@ -19,7 +71,7 @@ impl World {
         Self::default()
     }
 
-    pub fn add_resource(&mut self, resource_data: impl Any) {
+    pub fn add_resource(&mut self, resource_data: impl Any + Send + Sync) {
         self.resources.add(resource_data);
     }
 
@@ -48,31 +100,382 @@ impl World {
         self.resources.remove::<T>();
     }
 
+    /// Adds an `Events<T>` resource, unless there already is one, and has `update_events` update it.
+    pub fn add_event<T: Any + Send + Sync>(&mut self) {
+        if self.get_resource::<Events<T>>().is_none() {
+            self.add_resource(Events::<T>::default());
+            self.event_updaters.push(events::update_events::<T>);
+        }
+    }
+
+    pub fn send_event<T: Any + Send + Sync>(&mut self, event: T) -> Result<()> {
+        self.get_resource_mut::<Events<T>>()
+            .ok_or(CustomErrors::ResourceDoesNotExists)?
+            .send(event);
+        Ok(())
+    }
+
+    /// Moves every event channel added through `add_event` on by one cycle. `Schedule::run` does
+    /// this after its systems have run.
+    pub fn update_events(&mut self) {
+        for update in self.event_updaters.clone() {
+            update(self);
+        }
+    }
+
     pub fn register_component<T: Any>(&mut self) {
         self.entities.register_component::<T>();
     }
 
+    /// Registers `T` with hooks that run as the component is added to and removed from entities.
+    /// ```
+    /// use ecs_in_rust::{ComponentHooks, World};
+    /// struct Collider(f32);
+    /// struct Colliders(Vec<ecs_in_rust::Entity>);
+    ///
+    /// let mut world = World::new();
+    /// world.add_resource(Colliders(vec![]));
+    /// world.register_component_with_hooks(ComponentHooks::<Collider>::new().on_remove(
+    ///     |_collider, entity, commands| {
+    ///         commands.add(entity, move |world| {
+    ///             world.get_resource_mut::<Colliders>().unwrap().0.push(entity);
+    ///             Ok(())
+    ///         });
+    ///     },
+    /// ));
+    ///
+    /// let wall = world.spawn((Collider(1.0),))?;
+    /// world.delete_entity_by_id(wall)?;
+    /// assert_eq!(world.get_resource::<Colliders>().unwrap().0, vec![wall]);
+    /// # Ok::<(), eyre::Report>(())
+    /// ```
+    pub fn register_component_with_hooks<T: Any>(&mut self, hooks: ComponentHooks<T>) {
+        self.entities.register_component_with_hooks(hooks);
+    }
+
+    /// Applies the commands component hooks queued. The `World` methods that add or remove
+    /// components do so on their own, only components added through `create_entity` leave theirs
+    /// waiting for the next such call, or for this one.
+    pub fn flush(&mut self) -> Result<()> {
+        let mut errors = vec![];
+        loop {
+            let mut queue = self.entities.take_hook_commands();
+            if queue.is_empty() {
+                break;
+            }
+            errors.extend(queue.apply(self));
+        }
+        commands::into_result(errors)
+    }
+
     pub fn create_entity(&mut self) -> &mut Entities {
         self.entities.create_entity()
     }
 
-    pub fn query(&self) -> Query {
-        Query::new(&self.entities)
+    /// Creates an entity holding every component of the bundle, e.g.
+    /// `world.spawn((Location(0.0), Speed(2.0)))?`.
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Result<Entity> {
+        let entity = self.entities.spawn(bundle)?;
+        self.flush()?;
+        Ok(entity)
+    }
+
+    /// Spawns one entity per bundle, much faster than spawning them one at a time. Returns the
+    /// handles in the order of the bundles.
+    pub fn spawn_batch<B: Bundle>(
+        &mut self,
+        bundles: impl IntoIterator<Item = B>,
+    ) -> Result<Vec<Entity>> {
+        let entities = self.entities.spawn_batch(bundles)?;
+        self.flush()?;
+        Ok(entities)
     }
 
-    pub fn delete_component_by_entity_id<T: Any>(&mut self, index: usize) -> Result<()> {
-        self.entities.delete_component_by_entity_id::<T>(index)
+    pub fn insert_bundle<B: Bundle>(&mut self, entity: Entity, bundle: B) -> Result<()> {
+        self.entities.insert_bundle(entity, bundle)?;
+        self.flush()
     }
 
-    pub fn add_component_to_entity_by_id(&mut self, data: impl Any, index: usize) -> Result<()> {
-        self.entities.add_component_by_entity_id(data, index)
+    pub fn remove_bundle<B: Bundle>(&mut self, entity: Entity) -> Result<()> {
+        self.entities.remove_bundle::<B>(entity)?;
+        self.flush()
     }
 
-    pub fn delete_entity_by_id(&mut self, index: usize) -> Result<()> {
-        self.entities.delete_entity_by_id(index)?;
+    /// Query for every entity holding the components in `D`. Use `()` to only filter with
+    /// `with_component` and read the raw columns back through `run` or `run_entity`.
+    /// ```
+    /// use ecs_in_rust::World;
+    /// struct Location(f32);
+    /// struct Speed(f32);
+    ///
+    /// let mut world = World::new();
+    /// world.register_component::<Location>();
+    /// world.register_component::<Speed>();
+    /// world.create_entity().with_component(Location(0.0))?.with_component(Speed(2.0))?;
+    ///
+    /// for (_entity, mut location, speed) in world.query::<(&mut Location, &Speed)>()?.iter() {
+    ///     location.0 += speed.0;
+    /// }
+    ///
+    /// let (_entity, location) = world.query::<&Location>()?.iter().next().unwrap();
+    /// assert_eq!(location.0, 2.0);
+    /// # Ok::<(), eyre::Report>(())
+    /// ```
+    pub fn query<D: QueryData>(&self) -> Result<Query<'_, D>> {
+        Query::typed(&self.entities)
+    }
+
+    /// The tick that component insertions and writes through a `ComponentMut` are currently
+    /// stamped with.
+    pub fn change_tick(&self) -> u32 {
+        self.entities.change_tick()
+    }
+
+    /// Advance the change tick, returning the new one. Everything stamped before this call is
+    /// older than anything stamped after it.
+    pub fn increment_change_tick(&mut self) -> u32 {
+        self.entities.increment_change_tick()
+    }
+
+    /// Whether the entity behind this handle is still around. Handles kept past a
+    /// `delete_entity_by_id` are never alive again, even once their slot is reused.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    pub fn delete_component_by_entity_id<T: Any>(&mut self, entity: Entity) -> Result<()> {
+        self.entities.delete_component_by_entity_id::<T>(entity)?;
+        self.flush()
+    }
+
+    pub fn add_component_to_entity_by_id(
+        &mut self,
+        data: impl Any + Send + Sync,
+        entity: Entity,
+    ) -> Result<()> {
+        self.entities.add_component_by_entity_id(data, entity)?;
+        self.flush()
+    }
+
+    /// Deletes only the entity itself. It is taken out of its parent's `Children`, and its own
+    /// children are left without a parent; use `despawn_recursive` to delete them as well.
+    /// Relations pointing at it are removed, or their sources deleted, as their `OnDelete` says.
+    pub fn delete_entity_by_id(&mut self, entity: Entity) -> Result<()> {
+        hierarchy::detach(self, entity)?;
+        let doomed = relation::detach(self, entity)?;
+        self.entities.delete_entity_by_id(entity)?;
+        self.observers.forget(entity);
+
+        for source in doomed {
+            if self.is_alive(source) {
+                self.delete_entity_by_id(source)?;
+            }
+        }
+        self.flush()
+    }
+
+    /// Runs `observer` whenever an `E` is triggered, whatever it was fired at.
+    pub fn observe<E: EntityEvent>(
+        &mut self,
+        observer: impl FnMut(&mut Trigger<'_, E>, &mut Commands<'_, '_>) + Send + Sync + 'static,
+    ) {
+        self.observers.add(None, Box::new(observer));
+    }
+
+    /// Runs `observer` whenever an `E` reaches `entity`, until the entity is deleted.
+    pub fn observe_entity<E: EntityEvent>(
+        &mut self,
+        entity: Entity,
+        observer: impl FnMut(&mut Trigger<'_, E>, &mut Commands<'_, '_>) + Send + Sync + 'static,
+    ) -> Result<()> {
+        self.entities.index_of(entity)?;
+        self.observers.add(Some(entity), Box::new(observer));
         Ok(())
     }
+
+    /// Fires `event` at each of `targets`, or only at the global observers when there are none.
+    /// For every target its own observers run first, then the global ones; a propagating event
+    /// then does the same for each parent up the chain. Observers run right away, the commands
+    /// they queue are applied before this returns.
+    /// ```
+    /// use ecs_in_rust::{EntityEvent, World};
+    /// struct Damaged {
+    ///     amount: u32,
+    /// }
+    /// impl EntityEvent for Damaged {}
+    ///
+    /// let mut world = World::new();
+    /// let ship = world.create_entity().entity();
+    /// world.observe_entity::<Damaged>(ship, |trigger, commands| {
+    ///     if trigger.event().amount >= 10 {
+    ///         commands.despawn(trigger.entity().unwrap());
+    ///     }
+    /// })?;
+    ///
+    /// world.trigger(Damaged { amount: 4 }, [ship])?;
+    /// assert!(world.is_alive(ship));
+    /// world.trigger(Damaged { amount: 12 }, [ship])?;
+    /// assert!(!world.is_alive(ship));
+    /// # Ok::<(), eyre::Report>(())
+    /// ```
+    pub fn trigger<E: EntityEvent>(
+        &mut self,
+        event: E,
+        targets: impl IntoIterator<Item = Entity>,
+    ) -> Result<()> {
+        observer::trigger(self, event, targets.into_iter().collect())
+    }
+
+    /// Points `source` at `target` through the relation `R`, replacing its earlier target.
+    pub fn relate<R: Relation>(&mut self, source: Entity, target: Entity) -> Result<()> {
+        relation::relate::<R>(self, source, target)
+    }
+
+    pub fn unrelate<R: Relation>(&mut self, source: Entity) -> Result<()> {
+        relation::unrelate::<R>(self, source)
+    }
+
+    pub fn target<R: Relation>(&self, source: Entity) -> Result<Option<Entity>> {
+        relation::target_of::<R>(self, source)
+    }
+
+    /// Every entity whose `R` points at `target`, looked up in the reverse index.
+    pub fn sources<R: Relation>(&self, target: Entity) -> Vec<Entity> {
+        relation::sources_of::<R>(self, target)
+    }
+
+    pub fn add_child(&mut self, parent: Entity, child: Entity) -> Result<()> {
+        hierarchy::add_child(self, parent, child)?;
+        self.flush()
+    }
+
+    pub fn remove_child(&mut self, parent: Entity, child: Entity) -> Result<()> {
+        hierarchy::remove_child(self, parent, child)?;
+        self.flush()
+    }
+
+    pub fn parent(&self, entity: Entity) -> Result<Option<Entity>> {
+        hierarchy::parent_of(self, entity)
+    }
+
+    pub fn children(&self, entity: Entity) -> Result<Vec<Entity>> {
+        hierarchy::children_of(self, entity)
+    }
+
+    /// Deletes the entity together with all of its descendants.
+    pub fn despawn_recursive(&mut self, entity: Entity) -> Result<()> {
+        hierarchy::despawn_recursive(self, entity)
+    }
+
+    pub fn descendants_depth_first(&self, entity: Entity) -> Result<Descendants<'_>> {
+        Descendants::depth_first(self, entity)
+    }
+
+    pub fn descendants_breadth_first(&self, entity: Entity) -> Result<Descendants<'_>> {
+        Descendants::breadth_first(self, entity)
+    }
+
+    /// Writes every live entity with its components, and the resources, that `registry` has a
+    /// serializer for.
+    pub fn save_snapshot(&self, registry: &SerializerRegistry) -> Result<Vec<u8>> {
+        snapshot::save(self, registry)
+    }
+
+    /// Builds a fresh world out of a `save_snapshot`. Entities keep their handles.
+    pub fn load_snapshot(bytes: &[u8], registry: &SerializerRegistry) -> Result<World> {
+        snapshot::load(bytes, registry)
+    }
+
+    /// Spawns every entity written in a text scene. Each `entity { .. }` lists components by the
+    /// name they were registered under, with a `( .. )` or `{ .. }` body or none at all. Values
+    /// are numbers, `true`/`false`, strings, `[ .. ]` lists, `None`/`Some(..)` and nested bodies;
+    /// `//` starts a comment. Components named in the scene are registered with the world when
+    /// they aren't yet, and errors carry the line and column they were found at.
+    /// ```
+    /// use ecs_in_rust::{FromSceneValue, SceneRegistry, Value, World};
+    /// struct Location(f32, f32);
+    /// struct Health(u32);
+    ///
+    /// impl FromSceneValue for Location {
+    ///     fn from_value(value: &Value) -> eyre::Result<Self> {
+    ///         let elements = value.elements(2)?;
+    ///         Ok(Location(elements[0].parse()?, elements[1].parse()?))
+    ///     }
+    /// }
+    ///
+    /// impl FromSceneValue for Health {
+    ///     fn from_value(value: &Value) -> eyre::Result<Self> {
+    ///         Ok(Health(value.field("current")?.parse()?))
+    ///     }
+    /// }
+    ///
+    /// let mut registry = SceneRegistry::new();
+    /// registry
+    ///     .register_component::<Location>("Location")
+    ///     .register_component::<Health>("Health");
+    ///
+    /// let mut world = World::new();
+    /// let scene = "
+    ///     // the player
+    ///     entity {
+    ///         Location(1.0, 2.5)
+    ///         Health { current: 10 }
+    ///     }
+    /// ";
+    /// let spawned = world.spawn_scene(scene, &registry)?;
+    /// assert_eq!(spawned.len(), 1);
+    ///
+    /// let error = world.spawn_scene("entity { Health { current: -1 } }", &registry);
+    /// assert_eq!(error.unwrap_err().to_string(), "1:28: -1 doesn't fit into u32");
+    /// # Ok::<(), eyre::Report>(())
+    /// ```
+    pub fn spawn_scene(&mut self, source: &str, registry: &SceneRegistry) -> Result<Vec<Entity>> {
+        scene::spawn(self, source, registry)
+    }
+
+    /// Registers the component and resource types rollback snapshots copy, and how many
+    /// snapshots are kept.
+    pub fn rollback_mut(&mut self) -> &mut Rollback {
+        &mut self.rollback
+    }
+
+    pub fn rollback(&self) -> &Rollback {
+        &self.rollback
+    }
+
+    /// Keeps a copy of every entity, its components and the rollback resources as they are now,
+    /// to come back to with `rollback_to(tick)`.
+    pub fn save_rollback(&mut self, tick: u32) -> Result<()> {
+        rollback::save(self, tick)
+    }
+
+    /// Restores the entity map, the generations, the component data and the rollback resources
+    /// saved for `tick`, and forgets the snapshots taken after it.
+    pub fn rollback_to(&mut self, tick: u32) -> Result<()> {
+        rollback::restore(self, tick)?;
+        relation::rebuild_all(self)
+    }
+
+    /// Drop the removals `RemovedComponents` readers have been reporting, usually once a frame
+    /// after every reader had its turn.
+    pub fn clear_removed_components(&mut self) {
+        self.entities.clear_removed();
+    }
+
+    /// Drop the removals that were already recorded at the previous call. `Schedule::run` calls
+    /// this at the end of every run.
+    pub fn update_removed_components(&mut self) {
+        self.entities.update_removed();
+    }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<World>();
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use eyre::Result;
+
+use crate::{
+    commands::{self, CommandQueue, Commands},
+    entities::entity::Entity,
+    hierarchy, World,
+};
+
+/// Something fired at entities through `World::trigger`, like `struct Damaged { amount: u32 }`.
+pub trait EntityEvent: Any + Send + Sync {
+    /// Whether the event travels on to the target's parent, and its parent, once the target's
+    /// observers have run. Observers can still change this through `Trigger::propagate`.
+    const PROPAGATE: bool = false;
+}
+
+/// What an observer gets to see of the event it was triggered by.
+pub struct Trigger<'w, E> {
+    event: &'w E,
+    target: Option<Entity>,
+    entity: Option<Entity>,
+    propagate: bool,
+    world: &'w World,
+}
+
+impl<'w, E> Trigger<'w, E> {
+    pub fn event(&self) -> &'w E {
+        self.event
+    }
+
+    /// The entity the event was fired at, `None` when it was fired without targets.
+    pub fn target(&self) -> Option<Entity> {
+        self.target
+    }
+
+    /// The entity the event has reached, one of the target's ancestors while propagating.
+    pub fn entity(&self) -> Option<Entity> {
+        self.entity
+    }
+
+    pub fn world(&self) -> &'w World {
+        self.world
+    }
+
+    /// Lets the event go on to the next parent, or stops it there.
+    pub fn propagate(&mut self, propagate: bool) {
+        self.propagate = propagate;
+    }
+}
+
+type Observer<E> = Box<dyn FnMut(&mut Trigger<'_, E>, &mut Commands<'_, '_>) + Send + Sync>;
+type ObserverList = Box<dyn Any + Send + Sync>;
+
+/// Every observer, either watching an event type everywhere or only on one entity.
+#[derive(Default)]
+pub(crate) struct Observers {
+    global: HashMap<TypeId, ObserverList>,
+    entities: HashMap<Entity, HashMap<TypeId, ObserverList>>,
+}
+
+impl Observers {
+    pub(crate) fn add<E: EntityEvent>(&mut self, entity: Option<Entity>, observer: Observer<E>) {
+        let lists = match entity {
+            Some(entity) => self.entities.entry(entity).or_default(),
+            None => &mut self.global,
+        };
+        lists
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Vec::<Observer<E>>::new()))
+            .downcast_mut::<Vec<Observer<E>>>()
+            .unwrap()
+            .push(observer);
+    }
+
+    /// Drops the observers watching an entity that is gone.
+    pub(crate) fn forget(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+    }
+
+    /// Observers are taken out of the world while they run, so they can get to see it.
+    fn take<E: EntityEvent>(&mut self, entity: Option<Entity>) -> Vec<Observer<E>> {
+        let lists = match entity {
+            Some(entity) => self.entities.get_mut(&entity),
+            None => Some(&mut self.global),
+        };
+        lists
+            .and_then(|lists| lists.remove(&TypeId::of::<E>()))
+            .map(|list| *list.downcast::<Vec<Observer<E>>>().unwrap())
+            .unwrap_or_default()
+    }
+
+    fn put_back<E: EntityEvent>(&mut self, entity: Option<Entity>, observers: Vec<Observer<E>>) {
+        if observers.is_empty() {
+            return;
+        }
+        let lists = match entity {
+            Some(entity) => self.entities.entry(entity).or_default(),
+            None => &mut self.global,
+        };
+        lists.insert(TypeId::of::<E>(), Box::new(observers));
+    }
+}
+
+/// Runs the observers right away, then applies the commands they queued.
+pub(crate) fn trigger<E: EntityEvent>(
+    world: &mut World,
+    event: E,
+    targets: Vec<Entity>,
+) -> Result<()> {
+    let mut paths = Vec::with_capacity(targets.len());
+    for target in targets {
+        let mut path = vec![target];
+        while let Some(parent) = hierarchy::parent_of(world, path[path.len() - 1])? {
+            path.push(parent);
+        }
+        paths.push(path);
+    }
+
+    let mut global = world.observers.take::<E>(None);
+    let mut local = HashMap::new();
+    for entity in paths.iter().flatten() {
+        if !local.contains_key(entity) {
+            local.insert(*entity, world.observers.take::<E>(Some(*entity)));
+        }
+    }
+
+    let mut queue = CommandQueue::new();
+    run(world, &event, &paths, &mut global, &mut local, &mut queue);
+
+    world.observers.put_back(None, global);
+    for (entity, observers) in local {
+        world.observers.put_back(Some(entity), observers);
+    }
+
+    commands::into_result(queue.apply(world))?;
+    world.flush()
+}
+
+fn run<E: EntityEvent>(
+    world: &World,
+    event: &E,
+    paths: &[Vec<Entity>],
+    global: &mut [Observer<E>],
+    local: &mut HashMap<Entity, Vec<Observer<E>>>,
+    queue: &mut CommandQueue,
+) {
+    let mut commands = Commands::new(queue, world);
+    if paths.is_empty() {
+        let mut trigger = Trigger {
+            event,
+            target: None,
+            entity: None,
+            propagate: false,
+            world,
+        };
+        global
+            .iter_mut()
+            .for_each(|observer| observer(&mut trigger, &mut commands));
+    }
+
+    for path in paths {
+        let mut trigger = Trigger {
+            event,
+            target: Some(path[0]),
+            entity: None,
+            propagate: E::PROPAGATE,
+            world,
+        };
+        for entity in path {
+            trigger.entity = Some(*entity);
+            local
+                .get_mut(entity)
+                .unwrap()
+                .iter_mut()
+                .chain(global.iter_mut())
+                .for_each(|observer| observer(&mut trigger, &mut commands));
+            if !trigger.propagate {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Ping;
+    impl EntityEvent for Ping {}
+
+    #[test]
+    fn observers_of_deleted_entities_are_dropped() -> Result<()> {
+        let mut world = World::new();
+        let entity = world.create_entity().entity();
+        world.observe_entity::<Ping>(entity, |_trigger, _commands| {})?;
+        world.trigger(Ping, [entity])?;
+        assert_eq!(world.observers.entities[&entity].len(), 1);
+
+        world.delete_entity_by_id(entity)?;
+        assert!(world.observers.entities.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+    marker::PhantomData,
+};
+
+use eyre::Result;
+
+use crate::{
+    commands::Commands,
+    entities::{component_ref::ComponentRef, entity::Entity, hooks::ComponentHooks},
+    World,
+};
+
+/// What happens to the entities pointing at a target when the target is deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnDelete {
+    /// The relation is removed from them, which `RemovedComponents<Related<R>>` reports.
+    Remove,
+    /// They are deleted along with the target.
+    Delete,
+}
+
+/// A kind of relationship between two entities, like `struct Targets;`. An entity has at most
+/// one target per kind.
+pub trait Relation: Any + Send + Sync {
+    const ON_DELETE: OnDelete = OnDelete::Remove;
+}
+
+/// The component holding a relation's target. Only set through `World::relate`, which keeps the
+/// reverse index up to date.
+pub struct Related<R> {
+    target: Entity,
+    relation: PhantomData<fn() -> R>,
+}
+
+impl<R> Related<R> {
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+}
+
+impl<R> Clone for Related<R> {
+    fn clone(&self) -> Self {
+        Self {
+            target: self.target,
+            relation: PhantomData,
+        }
+    }
+}
+
+impl<R> fmt::Debug for Related<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Related").field(&self.target).finish()
+    }
+}
+
+type SourcesByTarget = HashMap<Entity, Vec<Entity>>;
+type Rebuild = fn(&World) -> Result<SourcesByTarget>;
+
+/// `targets` is the other direction of `sources`: the target each source is indexed under.
+struct RelationIndex {
+    sources: SourcesByTarget,
+    targets: HashMap<Entity, Entity>,
+    on_delete: OnDelete,
+    remove: fn(&mut World, Entity) -> Result<()>,
+    rebuild: Rebuild,
+}
+
+impl RelationIndex {
+    /// Moves `source` to wherever it points now, keeping its place when that didn't change.
+    fn reindex(&mut self, source: Entity, target: Option<Entity>) {
+        if self.targets.get(&source).copied() == target {
+            return;
+        }
+        if let Some(previous) = self.targets.remove(&source) {
+            unindex(&mut self.sources, source, previous);
+        }
+        if let Some(target) = target {
+            self.sources.entry(target).or_default().push(source);
+            self.targets.insert(source, target);
+        }
+    }
+}
+
+/// For every kind of relation in use, which entities point at each target.
+#[derive(Default)]
+pub struct Relations {
+    indices: HashMap<TypeId, RelationIndex>,
+}
+
+/// The index follows the `Related<R>` components through hooks, so it stays right however they
+/// are inserted or removed, `World::relate` or not.
+fn ensure_registered<R: Relation>(world: &mut World) {
+    if world.relations.indices.contains_key(&TypeId::of::<R>()) {
+        return;
+    }
+
+    world.register_component_with_hooks(
+        ComponentHooks::<Related<R>>::new()
+            .on_insert(queue_sync::<R>)
+            .on_remove(queue_sync::<R>),
+    );
+    world.relations.indices.insert(
+        TypeId::of::<R>(),
+        RelationIndex {
+            sources: HashMap::new(),
+            targets: HashMap::new(),
+            on_delete: R::ON_DELETE,
+            remove: remove_component::<R>,
+            rebuild: rebuild::<R>,
+        },
+    );
+}
+
+fn queue_sync<R: Relation>(_related: &Related<R>, source: Entity, commands: &mut Commands) {
+    commands.add(source, move |world| sync::<R>(world, source));
+}
+
+/// Brings the index in line with the `Related<R>` the source has right now, if any.
+fn sync<R: Relation>(world: &mut World, source: Entity) -> Result<()> {
+    let target = if world.is_alive(source) {
+        target_of::<R>(world, source)?
+    } else {
+        None
+    };
+    if let Some(index) = world.relations.indices.get_mut(&TypeId::of::<R>()) {
+        index.reindex(source, target);
+    }
+    Ok(())
+}
+
+fn unindex(sources: &mut SourcesByTarget, source: Entity, target: Entity) {
+    if let Some(pointing) = sources.get_mut(&target) {
+        pointing.retain(|entity| *entity != source);
+        if pointing.is_empty() {
+            sources.remove(&target);
+        }
+    }
+}
+
+pub(crate) fn target_of<R: Relation>(world: &World, source: Entity) -> Result<Option<Entity>> {
+    let index = world.entities.index_of(source)?;
+    let Some(entry) = world.entities.component(&TypeId::of::<Related<R>>(), index) else {
+        return Ok(None);
+    };
+    Ok(Some(
+        ComponentRef::<Related<R>>::new(&entry.component)?.target,
+    ))
+}
+
+/// Points `source` at `target`, replacing the target it had for `R` before.
+pub(crate) fn relate<R: Relation>(world: &mut World, source: Entity, target: Entity) -> Result<()> {
+    ensure_registered::<R>(world);
+    world.entities.index_of(target)?;
+    world.add_component_to_entity_by_id(
+        Related::<R> {
+            target,
+            relation: PhantomData,
+        },
+        source,
+    )
+}
+
+pub(crate) fn unrelate<R: Relation>(world: &mut World, source: Entity) -> Result<()> {
+    if target_of::<R>(world, source)?.is_none() {
+        return Ok(());
+    }
+    world.delete_component_by_entity_id::<Related<R>>(source)
+}
+
+fn remove_component<R: Relation>(world: &mut World, source: Entity) -> Result<()> {
+    world.delete_component_by_entity_id::<Related<R>>(source)
+}
+
+fn rebuild<R: Relation>(world: &World) -> Result<SourcesByTarget> {
+    let mut sources: SourcesByTarget = HashMap::new();
+    for (source, related) in world.query::<&Related<R>>()?.iter() {
+        sources.entry(related.target).or_default().push(source);
+    }
+    Ok(sources)
+}
+
+/// The entities whose `R` points at `target`, in the order they were related.
+pub(crate) fn sources_of<R: Relation>(world: &World, target: Entity) -> Vec<Entity> {
+    world
+        .relations
+        .indices
+        .get(&TypeId::of::<R>())
+        .and_then(|index| index.sources.get(&target))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Runs ahead of deleting `entity`: it leaves the index as a source, and whatever points at it
+/// is dealt with according to each relation's `OnDelete`. Returns the entities that have to be
+/// deleted along with it.
+pub(crate) fn detach(world: &mut World, entity: Entity) -> Result<Vec<Entity>> {
+    let kinds: Vec<TypeId> = world.relations.indices.keys().copied().collect();
+    let mut doomed = vec![];
+
+    for kind in kinds {
+        let index = world.relations.indices.get_mut(&kind).unwrap();
+        index.reindex(entity, None);
+
+        let sources = index.sources.remove(&entity).unwrap_or_default();
+        sources.iter().for_each(|source| {
+            index.targets.remove(source);
+        });
+        match index.on_delete {
+            OnDelete::Remove => {
+                let remove = index.remove;
+                for source in sources {
+                    remove(world, source)?;
+                }
+            }
+            OnDelete::Delete => doomed.extend(sources),
+        }
+    }
+
+    Ok(doomed)
+}
+
+/// Brings every index back in line with the `Related` components, after they were replaced
+/// wholesale, like on a rollback.
+pub(crate) fn rebuild_all(world: &mut World) -> Result<()> {
+    let kinds: Vec<(TypeId, Rebuild)> = world
+        .relations
+        .indices
+        .iter()
+        .map(|(kind, index)| (*kind, index.rebuild))
+        .collect();
+
+    for (kind, rebuild) in kinds {
+        let sources = rebuild(world)?;
+        let index = world.relations.indices.get_mut(&kind).unwrap();
+        index.targets = sources
+            .iter()
+            .flat_map(|(target, sources)| sources.iter().map(|source| (*source, *target)))
+            .collect();
+        index.sources = sources;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Follows;
+    impl Relation for Follows {}
+
+    #[test]
+    fn rebuilding_matches_the_live_index() -> Result<()> {
+        let mut world = World::new();
+        let leader = world.create_entity().entity();
+        let first = world.create_entity().entity();
+        let second = world.create_entity().entity();
+        relate::<Follows>(&mut world, first, leader)?;
+        relate::<Follows>(&mut world, second, leader)?;
+        relate::<Follows>(&mut world, second, first)?;
+
+        let live = world.relations.indices[&TypeId::of::<Follows>()]
+            .sources
+            .clone();
+        assert_eq!(rebuild::<Follows>(&world)?, live);
+        assert_eq!(live[&leader], vec![first]);
+        assert_eq!(live[&first], vec![second]);
+        Ok(())
+    }
+}
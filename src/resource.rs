@@ -5,11 +5,11 @@ use std::{
 
 #[derive(Default)]
 pub struct Resource {
-    data: HashMap<TypeId, Box<dyn Any>>,
+    data: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
 }
 
 impl Resource {
-    pub fn add(&mut self, data: impl Any) {
+    pub fn add(&mut self, data: impl Any + Send + Sync) {
         let type_id = data.type_id();
         self.data.insert(type_id, Box::new(data));
     }
@@ -32,6 +32,11 @@ impl Resource {
         }
     }
 
+    /// Every stored resource as its own mutable borrow.
+    pub fn slots_mut(&mut self) -> impl Iterator<Item = (TypeId, &mut Box<dyn Any + Send + Sync>)> {
+        self.data.iter_mut().map(|(type_id, data)| (*type_id, data))
+    }
+
     pub fn remove<T: Any>(&mut self) {
         let type_id = TypeId::of::<T>();
         self.data.remove(&type_id);
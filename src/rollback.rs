@@ -0,0 +1,224 @@
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use eyre::Result;
+
+use crate::{
+    custom_errors::CustomErrors,
+    entities::{component_ref::ComponentCell, Component, ComponentEntry, EntityLayout},
+    World,
+};
+
+const DEFAULT_CAPACITY: usize = 8;
+
+type CloneComponent = fn(&(dyn Any + Send + Sync)) -> Component;
+type SavedColumn = Vec<Option<(Component, u32, u32)>>;
+
+struct RollbackComponent {
+    type_id: TypeId,
+    clone: CloneComponent,
+}
+
+struct RollbackResource {
+    save: fn(&World) -> Option<Box<dyn Any + Send + Sync>>,
+    restore: fn(&mut World, Option<&(dyn Any + Send + Sync)>),
+}
+
+struct Snapshot {
+    tick: u32,
+    layout: EntityLayout,
+    columns: HashMap<TypeId, SavedColumn>,
+    resources: Vec<Option<Box<dyn Any + Send + Sync>>>,
+}
+
+/// Copies of the world from the last few ticks, oldest first, for rollback netcode. Components
+/// and resources are copied by cloning, so only types registered here take part, and every
+/// component type of the world has to be registered before a snapshot can be taken. Resources
+/// that aren't registered are left alone on a rollback.
+pub struct Rollback {
+    capacity: usize,
+    components: Vec<RollbackComponent>,
+    resources: Vec<RollbackResource>,
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl Default for Rollback {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            components: vec![],
+            resources: vec![],
+            snapshots: VecDeque::new(),
+        }
+    }
+}
+
+impl Rollback {
+    pub fn register_component<T: Any + Send + Sync + Clone>(&mut self) {
+        self.components.push(RollbackComponent {
+            type_id: TypeId::of::<T>(),
+            clone: clone_component::<T>,
+        });
+    }
+
+    pub fn register_resource<T: Any + Send + Sync + Clone>(&mut self) {
+        self.resources.push(RollbackResource {
+            save: save_resource::<T>,
+            restore: restore_resource::<T>,
+        });
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.drop_oldest();
+    }
+
+    /// The ticks there are snapshots of, oldest first.
+    pub fn ticks(&self) -> Vec<u32> {
+        self.snapshots
+            .iter()
+            .map(|snapshot| snapshot.tick)
+            .collect()
+    }
+
+    fn clone_fn(&self, type_id: &TypeId) -> Option<CloneComponent> {
+        self.components
+            .iter()
+            .find(|component| component.type_id == *type_id)
+            .map(|component| component.clone)
+    }
+
+    fn drop_oldest(&mut self) {
+        while self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+    }
+}
+
+fn clone_component<T: Any + Send + Sync + Clone>(component: &(dyn Any + Send + Sync)) -> Component {
+    let component = component.downcast_ref::<T>().unwrap().clone();
+    Arc::new(ComponentCell::new(component))
+}
+
+fn save_resource<T: Any + Send + Sync + Clone>(
+    world: &World,
+) -> Option<Box<dyn Any + Send + Sync>> {
+    world
+        .get_resource::<T>()
+        .map(|resource| Box::new(resource.clone()) as Box<dyn Any + Send + Sync>)
+}
+
+fn restore_resource<T: Any + Send + Sync + Clone>(
+    world: &mut World,
+    resource: Option<&(dyn Any + Send + Sync)>,
+) {
+    match resource.and_then(|resource| resource.downcast_ref::<T>()) {
+        Some(resource) => world.add_resource(resource.clone()),
+        None => world.delete_resource::<T>(),
+    }
+}
+
+fn copy_component(component: &Component, clone: CloneComponent) -> Result<Component> {
+    Ok(clone(&*component.read()?))
+}
+
+/// Takes a snapshot for `tick`, replacing an earlier one for the same tick and dropping the
+/// oldest once there are more than the capacity.
+pub(crate) fn save(world: &mut World, tick: u32) -> Result<()> {
+    let layout = world.entities.layout();
+
+    let mut columns = HashMap::new();
+    for type_id in world.entities.component_types() {
+        let clone = world.rollback.clone_fn(type_id).ok_or_else(|| {
+            CustomErrors::ComponentNotRegisteredForRollback(world.entities.component_name(type_id))
+        })?;
+
+        let column = world
+            .entities
+            .column(type_id)
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                entry
+                    .as_ref()
+                    .map(|entry| {
+                        let component = copy_component(&entry.component, clone)?;
+                        Ok((component, entry.added_tick, entry.changed_tick()))
+                    })
+                    .transpose()
+            })
+            .collect::<Result<SavedColumn>>()?;
+        columns.insert(*type_id, column);
+    }
+
+    let resources = world
+        .rollback
+        .resources
+        .iter()
+        .map(|resource| (resource.save)(world))
+        .collect();
+
+    let rollback = &mut world.rollback;
+    rollback.snapshots.retain(|snapshot| snapshot.tick != tick);
+    rollback.snapshots.push_back(Snapshot {
+        tick,
+        layout,
+        columns,
+        resources,
+    });
+    rollback.drop_oldest();
+    Ok(())
+}
+
+/// Puts the entities, their components and the registered resources back the way they were at
+/// `tick`. Snapshots taken after it are dropped, since those ticks are about to be simulated
+/// again. Component types registered after the snapshot come back empty.
+pub(crate) fn restore(world: &mut World, tick: u32) -> Result<()> {
+    let position = world
+        .rollback
+        .snapshots
+        .iter()
+        .position(|snapshot| snapshot.tick == tick)
+        .ok_or(CustomErrors::RollbackTickNotFound(tick))?;
+
+    let snapshot = &world.rollback.snapshots[position];
+    let slots = snapshot.layout.slots();
+    let mut columns = vec![];
+    for type_id in world.entities.component_types() {
+        let saved = snapshot.columns.get(type_id);
+        let clone = world.rollback.clone_fn(type_id);
+        let mut column: Vec<Option<ComponentEntry>> = Vec::with_capacity(slots);
+        match (saved, clone) {
+            (Some(saved), Some(clone)) => {
+                for entry in saved.iter() {
+                    let Some((component, added_tick, changed_tick)) = entry else {
+                        column.push(None);
+                        continue;
+                    };
+                    let entry = ComponentEntry::new(copy_component(component, clone)?, *added_tick);
+                    entry.mark_changed(*changed_tick);
+                    column.push(Some(entry));
+                }
+            }
+            _ => column.resize_with(slots, || None),
+        }
+        columns.push((*type_id, column));
+    }
+
+    world.entities.restore_layout(&snapshot.layout);
+    for (type_id, column) in columns {
+        world.entities.replace_column(type_id, column);
+    }
+
+    world.rollback.snapshots.truncate(position + 1);
+    let rollback = std::mem::take(&mut world.rollback);
+    let snapshot = &rollback.snapshots[position];
+    for (resource, saved) in rollback.resources.iter().zip(snapshot.resources.iter()) {
+        (resource.restore)(world, saved.as_deref());
+    }
+    world.rollback = rollback;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+pub mod parser;
+pub mod value;
+
+use std::any::{Any, TypeId};
+
+use eyre::Result;
+
+use crate::{entities::entity::Entity, World};
+
+use self::value::{FromSceneValue, Value};
+
+type InsertComponent = Box<dyn FnOnce(&mut World, Entity) -> Result<()>>;
+
+struct SceneComponent {
+    name: String,
+    prepare: fn(&Value) -> Result<InsertComponent>,
+}
+
+/// Maps the component names used in scene files to Rust types.
+#[derive(Default)]
+pub struct SceneRegistry {
+    components: Vec<SceneComponent>,
+}
+
+impl SceneRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_component<T: Any + Send + Sync + FromSceneValue>(
+        &mut self,
+        name: impl Into<String>,
+    ) -> &mut Self {
+        self.components.push(SceneComponent {
+            name: name.into(),
+            prepare: prepare_component::<T>,
+        });
+        self
+    }
+}
+
+fn prepare_component<T: Any + Send + Sync + FromSceneValue>(
+    value: &Value,
+) -> Result<InsertComponent> {
+    let component = T::from_value(value)?;
+    Ok(Box::new(move |world: &mut World, entity: Entity| {
+        if world.entities.get_bit(&TypeId::of::<T>()).is_none() {
+            world.register_component::<T>();
+        }
+        world.add_component_to_entity_by_id(component, entity)
+    }))
+}
+
+/// Parses the whole scene and converts every component before anything is spawned, so parse and
+/// conversion errors leave the world untouched. An insertion that fails part way keeps the
+/// entities spawned before it, and the failing entity holds only the components inserted so far.
+pub(crate) fn spawn(
+    world: &mut World,
+    source: &str,
+    registry: &SceneRegistry,
+) -> Result<Vec<Entity>> {
+    let mut prepared = vec![];
+    for record in parser::parse(source)? {
+        let mut components = vec![];
+        for component in record.components {
+            let scene_component = registry
+                .components
+                .iter()
+                .find(|scene_component| scene_component.name == component.name)
+                .ok_or_else(|| {
+                    component
+                        .position
+                        .error(format!("unknown component {}", component.name))
+                })?;
+            components.push((scene_component.prepare)(&component.body)?);
+        }
+        prepared.push(components);
+    }
+
+    let mut spawned = vec![];
+    for components in prepared {
+        let entity = world.create_entity().entity();
+        for insert in components {
+            insert(world, entity)?;
+        }
+        spawned.push(entity);
+    }
+    Ok(spawned)
+}
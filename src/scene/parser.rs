@@ -0,0 +1,323 @@
+use eyre::Result;
+
+use super::value::{Position, Value, ValueKind};
+
+/// One `entity { .. }` record: its components in the order they were written.
+#[derive(Debug, PartialEq)]
+pub struct EntityRecord {
+    pub position: Position,
+    pub components: Vec<ComponentRecord>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ComponentRecord {
+    pub name: String,
+    pub position: Position,
+    pub body: Value,
+}
+
+/// Parses a whole scene:
+///
+/// ```text
+/// // comments run to the end of the line
+/// entity {
+///     Location(1.0, 2.0)
+///     Health { current: 10, max: 10 }
+///     Inventory { items: ["sword", "rope"], gold: Some(3) }
+///     Player
+/// }
+/// ```
+pub fn parse(source: &str) -> Result<Vec<EntityRecord>> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        index: 0,
+        position: Position { line: 1, column: 1 },
+    };
+
+    let mut entities = vec![];
+    loop {
+        parser.skip_whitespace();
+        if parser.peek().is_none() {
+            return Ok(entities);
+        }
+        entities.push(parser.entity()?);
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+    position: Position,
+}
+
+impl Parser {
+    fn entity(&mut self) -> Result<EntityRecord> {
+        let position = self.position;
+        let keyword = self.ident()?;
+        if keyword != "entity" {
+            return Err(position.error(format!("expected entity, found {keyword}")));
+        }
+        self.expect('{')?;
+
+        let mut components = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.eat('}') {
+                return Ok(EntityRecord {
+                    position,
+                    components,
+                });
+            }
+
+            let position = self.position;
+            let name = self.ident()?;
+            let body = self.body()?;
+            components.push(ComponentRecord {
+                name,
+                position,
+                body,
+            });
+            self.skip_whitespace();
+            self.eat(',');
+        }
+    }
+
+    /// What follows a name: `( .. )`, `{ .. }`, or nothing at all.
+    fn body(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        let position = self.position;
+        let kind = match self.peek() {
+            Some('(') => ValueKind::Tuple(self.sequence('(', ')')?),
+            Some('{') => ValueKind::Struct(self.fields()?),
+            _ => ValueKind::Unit,
+        };
+        Ok(Value { kind, position })
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        let position = self.position;
+        let kind = match self.peek() {
+            Some('"') => ValueKind::String(self.string()?),
+            Some('[') => ValueKind::List(self.sequence('[', ']')?),
+            Some('(') => ValueKind::Tuple(self.sequence('(', ')')?),
+            Some('{') => ValueKind::Struct(self.fields()?),
+            Some(char) if char == '-' || char.is_ascii_digit() => self.number()?,
+            Some(char) if is_ident_start(char) => match self.ident()?.as_str() {
+                "true" => ValueKind::Bool(true),
+                "false" => ValueKind::Bool(false),
+                name => ValueKind::Named(name.to_owned(), Box::new(self.body()?)),
+            },
+            Some(char) => return Err(position.error(format!("unexpected {char:?}"))),
+            None => return Err(position.error("unexpected end of scene")),
+        };
+        Ok(Value { kind, position })
+    }
+
+    fn sequence(&mut self, open: char, close: char) -> Result<Vec<Value>> {
+        self.expect(open)?;
+        let mut values = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.eat(close) {
+                return Ok(values);
+            }
+            values.push(self.value()?);
+            self.skip_whitespace();
+            if !self.eat(',') {
+                self.expect(close)?;
+                return Ok(values);
+            }
+        }
+    }
+
+    fn fields(&mut self) -> Result<Vec<(String, Value)>> {
+        self.expect('{')?;
+        let mut fields = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.eat('}') {
+                return Ok(fields);
+            }
+            let name = self.ident()?;
+            self.expect(':')?;
+            fields.push((name, self.value()?));
+            self.skip_whitespace();
+            if !self.eat(',') {
+                self.expect('}')?;
+                return Ok(fields);
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<ValueKind> {
+        let position = self.position;
+        let mut text = String::new();
+        while let Some(char) = self.peek() {
+            if !(char.is_ascii_alphanumeric() || matches!(char, '-' | '+' | '.' | '_')) {
+                break;
+            }
+            text.push(char);
+            self.advance();
+        }
+
+        let text = text.replace('_', "");
+        if let Ok(int) = text.parse::<i64>() {
+            return Ok(ValueKind::Int(int));
+        }
+        text.parse::<f64>()
+            .map(ValueKind::Float)
+            .map_err(|_| position.error(format!("{text} is not a number")))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let position = self.position;
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(string),
+                Some('\\') => {
+                    let escape = self.position;
+                    match self.advance() {
+                        Some('n') => string.push('\n'),
+                        Some('t') => string.push('\t'),
+                        Some(char @ ('"' | '\\')) => string.push(char),
+                        _ => return Err(escape.error("unknown escape sequence")),
+                    }
+                }
+                Some(char) => string.push(char),
+                None => return Err(position.error("unterminated string")),
+            }
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let position = self.position;
+        let mut ident = String::new();
+        while let Some(char) = self.peek() {
+            if !(is_ident_start(char) || char.is_ascii_digit()) {
+                break;
+            }
+            ident.push(char);
+            self.advance();
+        }
+
+        if ident.is_empty() {
+            return Err(match self.peek() {
+                Some(char) => position.error(format!("expected a name, found {char:?}")),
+                None => position.error("expected a name, found the end of the scene"),
+            });
+        }
+        Ok(ident)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        if self.eat(expected) {
+            return Ok(());
+        }
+        Err(match self.peek() {
+            Some(char) => self
+                .position
+                .error(format!("expected {expected:?}, found {char:?}")),
+            None => self
+                .position
+                .error(format!("expected {expected:?}, found the end of the scene")),
+        })
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.advance();
+            return true;
+        }
+        false
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.peek() {
+                Some(char) if char.is_whitespace() => {
+                    self.advance();
+                }
+                Some('/') if self.chars.get(self.index + 1) == Some(&'/') => {
+                    while !matches!(self.advance(), Some('\n') | None) {}
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let char = self.peek()?;
+        self.index += 1;
+        if char == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(char)
+    }
+}
+
+fn is_ident_start(char: char) -> bool {
+    char.is_alphabetic() || char == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: usize, column: usize) -> Position {
+        Position { line, column }
+    }
+
+    #[test]
+    fn parses_components_and_values() -> Result<()> {
+        let scene = parse(
+            "// ships\nentity {\n  Location(1, -2.5)\n  Tag { name: \"a\\\"b\", on: true, ids: [1, 2,] },\n  Dead\n}\n",
+        )?;
+
+        assert_eq!(scene.len(), 1);
+        let components = &scene[0].components;
+        assert_eq!(scene[0].position, at(2, 1));
+        assert_eq!(components[0].name, "Location");
+        assert_eq!(components[0].position, at(3, 3));
+        assert_eq!(
+            components[0].body.kind,
+            ValueKind::Tuple(vec![
+                Value {
+                    kind: ValueKind::Int(1),
+                    position: at(3, 12)
+                },
+                Value {
+                    kind: ValueKind::Float(-2.5),
+                    position: at(3, 15)
+                },
+            ])
+        );
+        assert_eq!(
+            components[1].body.field("name")?.kind,
+            ValueKind::String("a\"b".to_owned())
+        );
+        assert_eq!(components[1].body.field("ids")?.position, at(4, 38));
+        assert_eq!(components[2].body.kind, ValueKind::Unit);
+        Ok(())
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        let error = parse("entity {\n  Location(1, 2\n}").err().unwrap();
+        assert_eq!(error.to_string(), "3:1: expected ')', found '}'");
+
+        let error = parse("entity {\n  Name(\"ship)\n}").err().unwrap();
+        assert_eq!(error.to_string(), "2:8: unterminated string");
+    }
+}
@@ -0,0 +1,178 @@
+use eyre::Result;
+
+use crate::custom_errors::CustomErrors;
+
+/// Where something starts in a scene file, both counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn error(&self, message: impl Into<String>) -> eyre::Report {
+        CustomErrors::Scene {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+        .into()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueKind {
+    /// A bare name, or a component without fields like `Dead`.
+    Unit,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    List(Vec<Value>),
+    Tuple(Vec<Value>),
+    Struct(Vec<(String, Value)>),
+    /// A name followed by a body, like `Some(3)`. Components are parsed into their body alone.
+    Named(String, Box<Value>),
+}
+
+/// A parsed value together with where it was written, so conversions can point at it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub kind: ValueKind,
+    pub position: Position,
+}
+
+impl Value {
+    pub fn parse<T: FromSceneValue>(&self) -> Result<T> {
+        T::from_value(self)
+    }
+
+    /// The field `name` of a `{ name: value }` body.
+    pub fn field(&self, name: &str) -> Result<&Value> {
+        let ValueKind::Struct(fields) = &self.kind else {
+            return Err(self.unexpected("fields in braces"));
+        };
+        fields
+            .iter()
+            .find(|(field, _value)| field == name)
+            .map(|(_field, value)| value)
+            .ok_or_else(|| self.position.error(format!("missing field {name}")))
+    }
+
+    /// The elements of a `(a, b)` body, which has to have exactly `len` of them.
+    pub fn elements(&self, len: usize) -> Result<&[Value]> {
+        let ValueKind::Tuple(elements) = &self.kind else {
+            return Err(self.unexpected("values in parentheses"));
+        };
+        if elements.len() != len {
+            return Err(self
+                .position
+                .error(format!("expected {len} values, found {}", elements.len())));
+        }
+        Ok(elements)
+    }
+
+    pub fn unexpected(&self, expected: &str) -> eyre::Report {
+        self.position
+            .error(format!("expected {expected}, found {}", self.describe()))
+    }
+
+    fn describe(&self) -> &'static str {
+        match self.kind {
+            ValueKind::Unit => "nothing",
+            ValueKind::Int(_) => "an integer",
+            ValueKind::Float(_) => "a float",
+            ValueKind::Bool(_) => "a bool",
+            ValueKind::String(_) => "a string",
+            ValueKind::List(_) => "a list",
+            ValueKind::Tuple(_) => "values in parentheses",
+            ValueKind::Struct(_) => "fields in braces",
+            ValueKind::Named(..) => "a name",
+        }
+    }
+}
+
+/// Turns a parsed scene value into a Rust value. Components implement it for their body: a
+/// tuple struct gets its `( .. )`, a struct with named fields its `{ .. }`.
+pub trait FromSceneValue: Sized {
+    fn from_value(value: &Value) -> Result<Self>;
+}
+
+macro_rules! impl_from_scene_value_for_int {
+    ($($int:ty),*) => {
+        $(
+            impl FromSceneValue for $int {
+                fn from_value(value: &Value) -> Result<Self> {
+                    let ValueKind::Int(int) = value.kind else {
+                        return Err(value.unexpected("an integer"));
+                    };
+                    <$int>::try_from(int).map_err(|_| {
+                        value
+                            .position
+                            .error(format!("{int} doesn't fit into {}", stringify!($int)))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_from_scene_value_for_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64);
+
+impl FromSceneValue for f64 {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value.kind {
+            ValueKind::Float(float) => Ok(float),
+            ValueKind::Int(int) => Ok(int as f64),
+            _ => Err(value.unexpected("a number")),
+        }
+    }
+}
+
+impl FromSceneValue for f32 {
+    fn from_value(value: &Value) -> Result<Self> {
+        Ok(f64::from_value(value)? as f32)
+    }
+}
+
+impl FromSceneValue for bool {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value.kind {
+            ValueKind::Bool(bool) => Ok(bool),
+            _ => Err(value.unexpected("a bool")),
+        }
+    }
+}
+
+impl FromSceneValue for String {
+    fn from_value(value: &Value) -> Result<Self> {
+        match &value.kind {
+            ValueKind::String(string) => Ok(string.clone()),
+            _ => Err(value.unexpected("a string")),
+        }
+    }
+}
+
+impl<T: FromSceneValue> FromSceneValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match &value.kind {
+            ValueKind::List(values) => values.iter().map(Value::parse).collect(),
+            _ => Err(value.unexpected("a list")),
+        }
+    }
+}
+
+/// Written as `None` or `Some(value)`.
+impl<T: FromSceneValue> FromSceneValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match &value.kind {
+            ValueKind::Named(name, body) if name == "None" && body.kind == ValueKind::Unit => {
+                Ok(None)
+            }
+            ValueKind::Named(name, body) if name == "Some" => {
+                Ok(Some(body.elements(1)?[0].parse()?))
+            }
+            _ => Err(value.unexpected("None or Some(..)")),
+        }
+    }
+}
@@ -0,0 +1,219 @@
+pub mod condition;
+pub mod config;
+pub mod executor;
+pub mod graph;
+
+use std::collections::HashMap;
+
+use eyre::{Result, WrapErr};
+
+use crate::{system::System, World};
+
+use self::{
+    condition::{Conditions, SystemSet},
+    config::{Constraints, IntoSystemConfig},
+    executor::ExecutorKind,
+    graph::Ordering,
+};
+
+/// A list of systems that all run with a single `run` call.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<Box<dyn System>>,
+    constraints: Vec<Constraints>,
+    conditions: Conditions,
+    executor: ExecutorKind,
+    ordering: Option<Ordering>,
+    batches: Option<Vec<Vec<usize>>>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_system<M>(&mut self, system: impl IntoSystemConfig<M>) -> Result<&mut Self> {
+        let config = system.into_config();
+        self.systems.push(config.system?);
+        self.constraints.push(config.constraints);
+        self.conditions.add_system(config.conditions, config.sets);
+        self.ordering = None;
+        self.batches = None;
+        Ok(self)
+    }
+
+    /// Adds run conditions to a set. A set can be configured before or after its systems are
+    /// added, and configuring it again adds to the conditions it already has.
+    pub fn configure_set(&mut self, set: SystemSet) -> &mut Self {
+        self.conditions.configure_set(set);
+        self
+    }
+
+    pub fn set_executor(&mut self, executor: ExecutorKind) -> &mut Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Runs every system in the order it was added, except where a `before`/`after` constraint
+    /// says otherwise. The change tick is advanced before each system, so what one system changes
+    /// is newer than anything the systems before it saw, and once more after the last one, so
+    /// changes made between runs are newer than anything any system saw. Stops at the first
+    /// system that fails.
+    ///
+    /// Event channels and the logs behind `RemovedComponents` are updated once every system has
+    /// run.
+    ///
+    /// With a parallel executor, systems whose accesses don't conflict run at the same time and
+    /// share a change tick; a batch always runs to completion before its errors are reported.
+    pub fn run(&mut self, world: &mut World) -> Result<()> {
+        if self.ordering.is_none() {
+            let names: Vec<&str> = self.systems.iter().map(|system| system.name()).collect();
+            self.ordering = Some(graph::sort(&names, &self.constraints)?);
+        }
+
+        let result = match self.executor {
+            ExecutorKind::SingleThreaded => self.run_single_threaded(world),
+            ExecutorKind::Parallel { threads } => self.run_parallel(world, threads),
+        };
+        world.increment_change_tick();
+        result?;
+
+        world.update_events();
+        world.update_removed_components();
+        Ok(())
+    }
+
+    fn run_single_threaded(&mut self, world: &mut World) -> Result<()> {
+        let Some(ordering) = &self.ordering else {
+            return Ok(());
+        };
+
+        let mut evaluated_sets = HashMap::new();
+        for &index in ordering.order.iter() {
+            if !self
+                .conditions
+                .should_run(index, world, &mut evaluated_sets)
+            {
+                continue;
+            }
+
+            let system = &mut self.systems[index];
+            world.increment_change_tick();
+            system
+                .run(world)
+                .wrap_err_with(|| format!("system {} failed", system.name()))?;
+        }
+
+        Ok(())
+    }
+
+    fn run_parallel(&mut self, world: &mut World, threads: usize) -> Result<()> {
+        let Some(ordering) = &self.ordering else {
+            return Ok(());
+        };
+        let batches = self
+            .batches
+            .get_or_insert_with(|| executor::batches(&self.systems, ordering));
+
+        let mut evaluated_sets = HashMap::new();
+        for batch in batches.iter() {
+            let batch: Vec<usize> = batch
+                .iter()
+                .copied()
+                .filter(|index| {
+                    self.conditions
+                        .should_run(*index, world, &mut evaluated_sets)
+                })
+                .collect();
+            if !batch.is_empty() {
+                executor::run_batch(&mut self.systems, &batch, world, threads)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    fn first(world: &mut World) {
+        world.get_resource_mut::<Log>().unwrap().0.push("first");
+    }
+
+    fn second(world: &mut World) {
+        world.get_resource_mut::<Log>().unwrap().0.push("second");
+    }
+
+    fn broken(_world: &mut World) -> Result<()> {
+        Err(eyre::eyre!("broken"))
+    }
+
+    #[test]
+    fn runs_systems_in_insertion_order() -> Result<()> {
+        let mut world = World::new();
+        world.add_resource(Log::default());
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(second)?.add_system(first)?;
+        schedule.run(&mut world)?;
+        schedule.run(&mut world)?;
+
+        let log = world.get_resource::<Log>().unwrap();
+        assert_eq!(log.0, vec!["second", "first", "second", "first"]);
+        Ok(())
+    }
+
+    #[test]
+    fn constraints_reorder_systems() -> Result<()> {
+        let mut world = World::new();
+        world.add_resource(Log::default());
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(second.label("second"))?
+            .add_system(first.before("second"))?;
+        schedule.run(&mut world)?;
+
+        let log = world.get_resource::<Log>().unwrap();
+        assert_eq!(log.0, vec!["first", "second"]);
+        Ok(())
+    }
+
+    #[test]
+    fn cycles_fail_the_run() -> Result<()> {
+        let mut world = World::new();
+        world.add_resource(Log::default());
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(first.label("first").after("second"))?
+            .add_system(second.label("second").after("first"))?;
+        let error = schedule.run(&mut world).unwrap_err();
+
+        assert!(error.to_string().contains("cycle"));
+        assert!(world.get_resource::<Log>().unwrap().0.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn stops_at_the_first_failing_system() {
+        let mut world = World::new();
+        world.add_resource(Log::default());
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(broken)
+            .unwrap()
+            .add_system(first)
+            .unwrap();
+        let error = schedule.run(&mut world).unwrap_err();
+
+        assert!(error.to_string().contains("broken"));
+        assert!(world.get_resource::<Log>().unwrap().0.is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+use std::{any::Any, collections::HashMap};
+
+use crate::{
+    events::{EventCursor, Events},
+    World,
+};
+
+/// A predicate checked right before a system is dispatched. The system is skipped for that run of
+/// the schedule when it returns `false`.
+pub type Condition = Box<dyn FnMut(&World) -> bool + Send>;
+
+pub fn resource_exists<T: Any>() -> impl FnMut(&World) -> bool + Send + 'static {
+    |world: &World| world.get_resource::<T>().is_some()
+}
+
+pub fn resource_equals<T: Any + PartialEq + Send>(
+    value: T,
+) -> impl FnMut(&World) -> bool + Send + 'static {
+    move |world: &World| world.get_resource::<T>() == Some(&value)
+}
+
+/// True when `T` events were sent since the last check. The condition keeps its own cursor, so
+/// it doesn't take the events away from any reader.
+pub fn on_event<T: Any + Send + Sync>() -> impl FnMut(&World) -> bool + Send + 'static {
+    let mut cursor = EventCursor::<T>::new();
+    move |world: &World| {
+        world
+            .get_resource::<Events<T>>()
+            .is_some_and(|events| cursor.read(events).count() > 0)
+    }
+}
+
+/// True the first time it is checked and then on every `n`th check after that.
+pub fn every_n_ticks(n: usize) -> impl FnMut(&World) -> bool + Send + 'static {
+    let mut checks = 0;
+    move |_world: &World| {
+        let run = checks % n.max(1) == 0;
+        checks += 1;
+        run
+    }
+}
+
+/// A named group of systems. Conditions on a set apply to every system in it, and the name can
+/// be used as a label in `before`/`after` constraints.
+pub struct SystemSet {
+    pub(crate) name: String,
+    pub(crate) conditions: Vec<Condition>,
+}
+
+impl SystemSet {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            conditions: vec![],
+        }
+    }
+
+    pub fn run_if(mut self, condition: impl FnMut(&World) -> bool + Send + 'static) -> Self {
+        self.conditions.push(Box::new(condition));
+        self
+    }
+}
+
+/// The conditions of every system in a schedule, plus those of the sets they belong to.
+#[derive(Default)]
+pub struct Conditions {
+    systems: Vec<Vec<Condition>>,
+    memberships: Vec<Vec<String>>,
+    sets: HashMap<String, Vec<Condition>>,
+}
+
+impl Conditions {
+    pub fn add_system(&mut self, conditions: Vec<Condition>, sets: Vec<String>) {
+        self.systems.push(conditions);
+        self.memberships.push(sets);
+    }
+
+    pub fn configure_set(&mut self, set: SystemSet) {
+        self.sets
+            .entry(set.name)
+            .or_default()
+            .extend(set.conditions);
+    }
+
+    /// Checks every condition of the system at `index` and of its sets. A set's conditions are
+    /// only checked once per schedule run, `evaluated_sets` keeps their answers until then.
+    /// Every condition is checked even after one of them said no, so stateful conditions like
+    /// `every_n_ticks` stay in step.
+    pub fn should_run(
+        &mut self,
+        index: usize,
+        world: &World,
+        evaluated_sets: &mut HashMap<String, bool>,
+    ) -> bool {
+        let mut run = true;
+
+        for set in self.memberships[index].iter() {
+            let set_runs = match evaluated_sets.get(set) {
+                Some(set_runs) => *set_runs,
+                None => {
+                    let set_runs = check_all(self.sets.get_mut(set), world);
+                    evaluated_sets.insert(set.clone(), set_runs);
+                    set_runs
+                }
+            };
+            run &= set_runs;
+        }
+
+        run & check_all(self.systems.get_mut(index), world)
+    }
+}
+
+fn check_all(conditions: Option<&mut Vec<Condition>>, world: &World) -> bool {
+    conditions
+        .into_iter()
+        .flatten()
+        .fold(true, |run, condition| condition(world) & run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(PartialEq)]
+    enum GameState {
+        Paused,
+        Running,
+    }
+
+    #[test]
+    fn resource_conditions() {
+        let mut world = World::new();
+        let mut exists = resource_exists::<GameState>();
+        let mut running = resource_equals(GameState::Running);
+
+        assert!(!exists(&world));
+        assert!(!running(&world));
+
+        world.add_resource(GameState::Paused);
+        assert!(exists(&world));
+        assert!(!running(&world));
+
+        world.add_resource(GameState::Running);
+        assert!(running(&world));
+    }
+
+    #[test]
+    fn every_n_ticks_skips_in_between() {
+        let world = World::new();
+        let mut every_third = every_n_ticks(3);
+
+        let runs: Vec<bool> = (0..7).map(|_| every_third(&world)).collect();
+
+        assert_eq!(runs, vec![true, false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn set_conditions_are_checked_once_per_run() {
+        let world = World::new();
+        let mut conditions = Conditions::default();
+        conditions.add_system(vec![], vec!["slow".to_owned()]);
+        conditions.add_system(
+            vec![Box::new(|_world: &World| true)],
+            vec!["slow".to_owned()],
+        );
+        conditions.configure_set(SystemSet::new("slow").run_if(every_n_ticks(2)));
+
+        let mut runs = vec![];
+        for _ in 0..2 {
+            let mut evaluated_sets = HashMap::new();
+            runs.push(conditions.should_run(0, &world, &mut evaluated_sets));
+            runs.push(conditions.should_run(1, &world, &mut evaluated_sets));
+        }
+
+        assert_eq!(runs, vec![true, true, false, false]);
+    }
+}
@@ -0,0 +1,101 @@
+use eyre::Result;
+
+use crate::{
+    system::{IntoSystem, System},
+    World,
+};
+
+use super::condition::Condition;
+
+/// A system together with the labels it carries, the labels it has to run before or after, the
+/// sets it belongs to and the conditions it only runs under.
+pub struct SystemConfig {
+    pub(crate) system: Result<Box<dyn System>>,
+    pub(crate) constraints: Constraints,
+    pub(crate) sets: Vec<String>,
+    pub(crate) conditions: Vec<Condition>,
+}
+
+#[derive(Default)]
+pub struct Constraints {
+    pub labels: Vec<String>,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+impl SystemConfig {
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.constraints.labels.push(label.into());
+        self
+    }
+
+    pub fn before(mut self, label: impl Into<String>) -> Self {
+        self.constraints.before.push(label.into());
+        self
+    }
+
+    pub fn after(mut self, label: impl Into<String>) -> Self {
+        self.constraints.after.push(label.into());
+        self
+    }
+
+    /// Puts the system into a set, which also labels it with the set's name.
+    pub fn in_set(mut self, set: impl Into<String>) -> Self {
+        let set = set.into();
+        self.constraints.labels.push(set.clone());
+        self.sets.push(set);
+        self
+    }
+
+    pub fn run_if(mut self, condition: impl FnMut(&World) -> bool + Send + 'static) -> Self {
+        self.conditions.push(Box::new(condition));
+        self
+    }
+}
+
+/// Anything that can be added to a `Schedule`: a system on its own, or one that has already been
+/// given labels or ordering constraints.
+pub trait IntoSystemConfig<Marker>: Sized {
+    fn into_config(self) -> SystemConfig;
+
+    fn label(self, label: impl Into<String>) -> SystemConfig {
+        self.into_config().label(label)
+    }
+
+    fn before(self, label: impl Into<String>) -> SystemConfig {
+        self.into_config().before(label)
+    }
+
+    fn after(self, label: impl Into<String>) -> SystemConfig {
+        self.into_config().after(label)
+    }
+
+    fn in_set(self, set: impl Into<String>) -> SystemConfig {
+        self.into_config().in_set(set)
+    }
+
+    fn run_if(self, condition: impl FnMut(&World) -> bool + Send + 'static) -> SystemConfig {
+        self.into_config().run_if(condition)
+    }
+}
+
+impl<Marker, S: IntoSystem<Marker>> IntoSystemConfig<Marker> for S {
+    fn into_config(self) -> SystemConfig {
+        SystemConfig {
+            system: self
+                .into_system()
+                .map(|system| Box::new(system) as Box<dyn System>),
+            constraints: Constraints::default(),
+            sets: vec![],
+            conditions: vec![],
+        }
+    }
+}
+
+pub struct SystemConfigMarker;
+
+impl IntoSystemConfig<SystemConfigMarker> for SystemConfig {
+    fn into_config(self) -> SystemConfig {
+        self
+    }
+}
@@ -0,0 +1,201 @@
+use std::{num::NonZeroUsize, sync::Mutex, thread};
+
+use eyre::{Result, WrapErr};
+
+use crate::{
+    system::{access::Access, system_param::SystemView, System},
+    World,
+};
+
+use super::graph::Ordering;
+
+/// How a `Schedule` dispatches its systems.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutorKind {
+    /// One system after the other on the calling thread.
+    #[default]
+    SingleThreaded,
+    /// Systems whose accesses don't conflict run together on a pool of `threads` workers.
+    Parallel { threads: usize },
+}
+
+impl ExecutorKind {
+    /// A parallel executor with one worker per available core.
+    pub fn parallel() -> Self {
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        ExecutorKind::Parallel { threads }
+    }
+}
+
+/// Groups systems, in order, into batches that can run at the same time. A system joins the
+/// current batch when it is compatible with everything already in there and none of them has to
+/// run before it, so two conflicting systems always keep their order. Systems without an access
+/// get a batch of their own. Every batch is sorted by system index.
+pub fn batches(systems: &[Box<dyn System>], ordering: &Ordering) -> Vec<Vec<usize>> {
+    let mut batches: Vec<Vec<usize>> = vec![];
+    let mut current: Vec<usize> = vec![];
+    let mut current_accesses: Vec<&Access> = vec![];
+
+    for &index in ordering.order.iter() {
+        let system = &systems[index];
+        let Some(access) = system.access() else {
+            if !current.is_empty() {
+                batches.push(std::mem::take(&mut current));
+                current_accesses.clear();
+            }
+            batches.push(vec![index]);
+            continue;
+        };
+
+        let waits_on_current = ordering.predecessors[index]
+            .iter()
+            .any(|before| current.contains(before));
+        if waits_on_current
+            || !current_accesses
+                .iter()
+                .all(|other| other.is_compatible(access))
+        {
+            batches.push(std::mem::take(&mut current));
+            current_accesses.clear();
+        }
+        current.push(index);
+        current_accesses.push(access);
+    }
+
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    for batch in batches.iter_mut() {
+        batch.sort_unstable();
+    }
+    batches
+}
+
+/// Runs one batch from `batches`, spreading it over at most `threads` workers. The change tick
+/// is advanced once for the whole batch, and what the systems deferred is applied in batch order
+/// after all of them are done.
+pub fn run_batch(
+    systems: &mut [Box<dyn System>],
+    batch: &[usize],
+    world: &mut World,
+    threads: usize,
+) -> Result<()> {
+    world.increment_change_tick();
+
+    if let [index] = batch {
+        let system = &mut systems[*index];
+        return system
+            .run(world)
+            .wrap_err_with(|| format!("system {} failed", system.name()));
+    }
+
+    let views = {
+        let accesses: Vec<&Access> = batch
+            .iter()
+            .map(|index| systems[*index].access().unwrap())
+            .collect();
+        SystemView::split(world, &accesses)
+    };
+
+    let jobs: Vec<_> = systems
+        .iter_mut()
+        .enumerate()
+        .filter(|(index, _system)| batch.contains(index))
+        .map(|(_index, system)| system)
+        .zip(views)
+        .enumerate()
+        .collect();
+    let workers = threads.clamp(1, jobs.len());
+    let jobs = Mutex::new(jobs);
+    let results = Mutex::new(vec![]);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let Some((position, (system, view))) = jobs.lock().unwrap().pop() else {
+                    break;
+                };
+                let result = system
+                    .run_in_view(view)
+                    .wrap_err_with(|| format!("system {} failed", system.name()));
+                results.lock().unwrap().push((position, result));
+            });
+        }
+    });
+
+    drop(jobs);
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(position, _result)| *position);
+    for (position, result) in results {
+        result?;
+        let system = &mut systems[batch[position]];
+        system
+            .apply_deferred(world)
+            .wrap_err_with(|| format!("system {} failed", system.name()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        schedule::{config::Constraints, graph::sort},
+        IntoSystem, Res, ResMut,
+    };
+
+    use super::*;
+
+    struct Score;
+    struct Time;
+
+    fn read_score(_score: Res<Score>) {}
+    fn read_time(_time: Res<Time>) {}
+    fn write_score(_score: ResMut<Score>) {}
+    fn exclusive(_world: &mut World) {}
+
+    fn boxed<M>(system: impl IntoSystem<M>) -> Box<dyn System> {
+        Box::new(system.into_system().unwrap())
+    }
+
+    #[test]
+    fn conflicting_systems_end_up_in_separate_batches() {
+        let systems = vec![
+            boxed(read_score),
+            boxed(read_time),
+            boxed(write_score),
+            boxed(read_time),
+            boxed(exclusive),
+            boxed(read_score),
+        ];
+
+        let constraints: Vec<Constraints> =
+            systems.iter().map(|_| Constraints::default()).collect();
+        let ordering = sort(&["system"; 6], &constraints).unwrap();
+
+        assert_eq!(
+            batches(&systems, &ordering),
+            vec![vec![0, 1], vec![2, 3], vec![4], vec![5]]
+        );
+    }
+
+    #[test]
+    fn ordered_systems_end_up_in_separate_batches() {
+        let systems = vec![boxed(read_score), boxed(read_time), boxed(read_score)];
+        let constraints = vec![
+            Constraints {
+                after: vec!["time".to_owned()],
+                ..Constraints::default()
+            },
+            Constraints {
+                labels: vec!["time".to_owned()],
+                ..Constraints::default()
+            },
+            Constraints::default(),
+        ];
+        let ordering = sort(&["system"; 3], &constraints).unwrap();
+
+        assert_eq!(batches(&systems, &ordering), vec![vec![1], vec![0, 2]]);
+    }
+}
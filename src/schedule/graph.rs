@@ -0,0 +1,153 @@
+use std::collections::{BTreeSet, HashMap};
+
+use eyre::Result;
+
+use crate::custom_errors::CustomErrors;
+
+use super::config::Constraints;
+
+/// The order systems run in, and for every system the systems that have to finish before it.
+pub struct Ordering {
+    pub order: Vec<usize>,
+    pub predecessors: Vec<Vec<usize>>,
+}
+
+/// Sorts systems so every `before`/`after` constraint holds. Systems that aren't constrained
+/// against each other keep the order they were added in. Constraints on a label that no system
+/// carries are ignored.
+pub fn sort(names: &[&str], constraints: &[Constraints]) -> Result<Ordering> {
+    let mut labelled: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, constraint) in constraints.iter().enumerate() {
+        for label in constraint.labels.iter() {
+            labelled.entry(label).or_default().push(index);
+        }
+    }
+
+    let mut predecessors: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); constraints.len()];
+    for (index, constraint) in constraints.iter().enumerate() {
+        for label in constraint.after.iter() {
+            for &other in labelled.get(label.as_str()).into_iter().flatten() {
+                predecessors[index].insert(other);
+            }
+        }
+        for label in constraint.before.iter() {
+            for &other in labelled.get(label.as_str()).into_iter().flatten() {
+                predecessors[other].insert(index);
+            }
+        }
+    }
+
+    let mut successors = vec![vec![]; constraints.len()];
+    for (index, before) in predecessors.iter().enumerate() {
+        for &other in before {
+            successors[other].push(index);
+        }
+    }
+
+    let mut waiting_on: Vec<usize> = predecessors.iter().map(BTreeSet::len).collect();
+    let mut ready: BTreeSet<usize> = (0..constraints.len())
+        .filter(|index| waiting_on[*index] == 0)
+        .collect();
+    let mut order = Vec::with_capacity(constraints.len());
+
+    while let Some(index) = ready.pop_first() {
+        order.push(index);
+        for &next in successors[index].iter() {
+            waiting_on[next] -= 1;
+            if waiting_on[next] == 0 {
+                ready.insert(next);
+            }
+        }
+    }
+
+    if order.len() < constraints.len() {
+        let cycle = find_cycle(&predecessors, &waiting_on);
+        let names: Vec<&str> = cycle.into_iter().map(|index| names[index]).collect();
+        return Err(CustomErrors::SystemOrderCycle(names.join(" -> ")).into());
+    }
+
+    Ok(Ordering {
+        order,
+        predecessors: predecessors
+            .into_iter()
+            .map(|before| before.into_iter().collect())
+            .collect(),
+    })
+}
+
+/// Every system the sort couldn't place still waits on another unplaced system, so walking
+/// backwards from any of them has to run into a cycle.
+fn find_cycle(predecessors: &[BTreeSet<usize>], waiting_on: &[usize]) -> Vec<usize> {
+    let unplaced = |index: &usize| waiting_on[*index] > 0;
+    let mut path = vec![(0..waiting_on.len()).find(unplaced).unwrap()];
+
+    loop {
+        let current = *path.last().unwrap();
+        let previous = *predecessors[current]
+            .iter()
+            .find(|index| unplaced(index))
+            .unwrap();
+        if let Some(start) = path.iter().position(|index| *index == previous) {
+            let mut cycle = path.split_off(start);
+            cycle.reverse();
+            let first = cycle
+                .iter()
+                .position(|index| Some(index) == cycle.iter().min());
+            cycle.rotate_left(first.unwrap());
+            cycle.push(cycle[0]);
+            return cycle;
+        }
+        path.push(previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constraints(labels: &[&str], before: &[&str], after: &[&str]) -> Constraints {
+        let strings = |labels: &[&str]| labels.iter().map(|label| label.to_string()).collect();
+        Constraints {
+            labels: strings(labels),
+            before: strings(before),
+            after: strings(after),
+        }
+    }
+
+    #[test]
+    fn constraints_override_insertion_order() {
+        let systems = [
+            constraints(&["despawn"], &[], &[]),
+            constraints(&["damage"], &["despawn"], &["collision"]),
+            constraints(&["collision"], &[], &[]),
+            constraints(&[], &[], &["missing"]),
+        ];
+
+        let ordering = sort(&["despawn", "damage", "collision", "free"], &systems).unwrap();
+
+        assert_eq!(ordering.order, vec![2, 1, 0, 3]);
+        assert_eq!(
+            ordering.predecessors,
+            vec![vec![1], vec![2], vec![], vec![]]
+        );
+    }
+
+    #[test]
+    fn cycles_name_the_systems_involved() {
+        let systems = [
+            constraints(&["a"], &[], &["c"]),
+            constraints(&["b"], &[], &["a"]),
+            constraints(&["c"], &[], &["b"]),
+            constraints(&[], &[], &["a"]),
+        ];
+
+        let error = sort(&["first", "second", "third", "downstream"], &systems)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "systems have to run in a cycle: first -> second -> third -> first"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+pub mod binary;
+
+use std::any::{Any, TypeId};
+
+use eyre::{Result, WrapErr};
+
+use crate::{custom_errors::CustomErrors, entities::entity::Entity, World};
+
+use self::binary::{read_len, write_len, Binary, Reader};
+
+const MAGIC: &[u8; 4] = b"ECSW";
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+struct ComponentSerializer {
+    name: String,
+    type_id: TypeId,
+    register: fn(&mut World),
+    write: fn(&(dyn Any + Send + Sync), &mut Vec<u8>) -> Result<()>,
+    insert: fn(&mut World, Entity, &mut Reader<'_>) -> Result<()>,
+}
+
+struct ResourceSerializer {
+    name: String,
+    write: fn(&World, &mut Vec<u8>) -> bool,
+    insert: fn(&mut World, &mut Reader<'_>) -> Result<()>,
+}
+
+/// The component and resource types a snapshot knows how to write and read. Types are stored
+/// under the given name rather than their `TypeId`, so snapshots keep working across builds.
+/// Components and resources without a serializer are left out of snapshots.
+#[derive(Default)]
+pub struct SerializerRegistry {
+    components: Vec<ComponentSerializer>,
+    resources: Vec<ResourceSerializer>,
+}
+
+impl SerializerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_component<T: Any + Send + Sync + Binary>(
+        &mut self,
+        name: impl Into<String>,
+    ) -> &mut Self {
+        self.components.push(ComponentSerializer {
+            name: name.into(),
+            type_id: TypeId::of::<T>(),
+            register: World::register_component::<T>,
+            write: write_component::<T>,
+            insert: insert_component::<T>,
+        });
+        self
+    }
+
+    pub fn register_resource<T: Any + Send + Sync + Binary>(
+        &mut self,
+        name: impl Into<String>,
+    ) -> &mut Self {
+        self.resources.push(ResourceSerializer {
+            name: name.into(),
+            write: write_resource::<T>,
+            insert: insert_resource::<T>,
+        });
+        self
+    }
+}
+
+fn write_component<T: Any + Binary>(
+    component: &(dyn Any + Send + Sync),
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    component
+        .downcast_ref::<T>()
+        .ok_or(CustomErrors::DowncastsToWrongType)?
+        .write(out);
+    Ok(())
+}
+
+fn insert_component<T: Any + Send + Sync + Binary>(
+    world: &mut World,
+    entity: Entity,
+    reader: &mut Reader<'_>,
+) -> Result<()> {
+    world.add_component_to_entity_by_id(reader.read::<T>()?, entity)
+}
+
+fn write_resource<T: Any + Binary>(world: &World, out: &mut Vec<u8>) -> bool {
+    world
+        .get_resource::<T>()
+        .map(|resource| resource.write(out))
+        .is_some()
+}
+
+fn insert_resource<T: Any + Send + Sync + Binary>(
+    world: &mut World,
+    reader: &mut Reader<'_>,
+) -> Result<()> {
+    world.add_resource(reader.read::<T>()?);
+    Ok(())
+}
+
+/// Layout, after the magic bytes and the version: the names of the registered components, the
+/// generation of every entity slot, every live entity as its index followed by its components,
+/// then the resources by name. Every component and resource is prefixed with its length in bytes.
+pub(crate) fn save(world: &World, registry: &SerializerRegistry) -> Result<Vec<u8>> {
+    let mut out = MAGIC.to_vec();
+    SNAPSHOT_VERSION.write(&mut out);
+
+    let names: Vec<String> = registry
+        .components
+        .iter()
+        .map(|serializer| serializer.name.clone())
+        .collect();
+    names.write(&mut out);
+
+    let generations = world.entities.generations();
+    u32::try_from(generations.len()).map_err(|_| {
+        CustomErrors::InvalidSnapshot(format!("{} entity slots", generations.len()))
+    })?;
+    generations.to_vec().write(&mut out);
+
+    let entities: Vec<Entity> = world.entities.live_entities().collect();
+    write_len(entities.len(), &mut out);
+    for entity in entities {
+        let index = u32::try_from(entity.index)
+            .map_err(|_| CustomErrors::InvalidSnapshot(format!("entity index {}", entity.index)))?;
+        index.write(&mut out);
+
+        let mut components = vec![];
+        for (position, serializer) in registry.components.iter().enumerate() {
+            let Some(entry) = world.entities.component(&serializer.type_id, entity.index) else {
+                continue;
+            };
+            let mut bytes = vec![];
+            (serializer.write)(&*entry.component.read()?, &mut bytes)?;
+            components.push((position as u32, bytes));
+        }
+        components.write(&mut out);
+    }
+
+    let mut resources = vec![];
+    for serializer in registry.resources.iter() {
+        let mut bytes = vec![];
+        if (serializer.write)(world, &mut bytes) {
+            resources.push((serializer.name.clone(), bytes));
+        }
+    }
+    resources.write(&mut out);
+
+    Ok(out)
+}
+
+pub(crate) fn load(bytes: &[u8], registry: &SerializerRegistry) -> Result<World> {
+    let mut reader = Reader::new(bytes);
+    if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(CustomErrors::InvalidSnapshot("not a world snapshot".to_owned()).into());
+    }
+    let version = reader.read::<u16>()?;
+    if version != SNAPSHOT_VERSION {
+        return Err(CustomErrors::UnsupportedSnapshotVersion(version).into());
+    }
+
+    let mut world = World::new();
+    for serializer in registry.components.iter() {
+        (serializer.register)(&mut world);
+    }
+
+    let components = reader
+        .read::<Vec<String>>()?
+        .into_iter()
+        .map(|name| {
+            registry
+                .components
+                .iter()
+                .find(|serializer| serializer.name == name)
+                .ok_or_else(|| CustomErrors::SerializerNotRegistered(name).into())
+        })
+        .collect::<Result<Vec<&ComponentSerializer>>>()?;
+
+    // Every slot costs four bytes of input, which keeps a corrupt snapshot from growing the
+    // storage past what it actually contains.
+    world.entities.restore_slots(&reader.read::<Vec<u32>>()?);
+
+    for _ in 0..read_len(&mut reader)? {
+        let index = reader.read::<u32>()? as usize;
+        let entity = world.entities.restore_entity(index).map_err(|_| {
+            CustomErrors::InvalidSnapshot(format!("entity {index} is not a free slot"))
+        })?;
+
+        for _ in 0..read_len(&mut reader)? {
+            let position = reader.read::<u32>()? as usize;
+            let serializer = components.get(position).ok_or_else(|| {
+                CustomErrors::InvalidSnapshot(format!("unknown component {position}"))
+            })?;
+            let len = read_len(&mut reader)?;
+            read_exactly(reader.take(len)?, |reader| {
+                (serializer.insert)(&mut world, entity, reader)
+            })
+            .wrap_err_with(|| format!("failed to load {} of {entity:?}", serializer.name))?;
+        }
+    }
+
+    world.entities.free_dead_slots();
+
+    for _ in 0..read_len(&mut reader)? {
+        let name = reader.read::<String>()?;
+        let serializer = registry
+            .resources
+            .iter()
+            .find(|serializer| serializer.name == name)
+            .ok_or_else(|| CustomErrors::SerializerNotRegistered(name.clone()))?;
+        let len = read_len(&mut reader)?;
+        read_exactly(reader.take(len)?, |reader| {
+            (serializer.insert)(&mut world, reader)
+        })
+        .wrap_err_with(|| format!("failed to load resource {name}"))?;
+    }
+
+    if !reader.is_empty() {
+        return Err(CustomErrors::InvalidSnapshot("trailing bytes".to_owned()).into());
+    }
+
+    Ok(world)
+}
+
+/// Reads a length-prefixed value, failing when the serializer doesn't use up all of its bytes.
+fn read_exactly(bytes: &[u8], read: impl FnOnce(&mut Reader<'_>) -> Result<()>) -> Result<()> {
+    let mut reader = Reader::new(bytes);
+    read(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(CustomErrors::InvalidSnapshot(
+            "a serializer read fewer bytes than were written".to_owned(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+
+    impl Binary for Health {
+        fn write(&self, out: &mut Vec<u8>) {
+            self.0.write(out);
+        }
+
+        fn read(reader: &mut Reader<'_>) -> Result<Self> {
+            Ok(Health(reader.read()?))
+        }
+    }
+
+    fn registry() -> SerializerRegistry {
+        let mut registry = SerializerRegistry::new();
+        registry.register_component::<Health>("health");
+        registry
+    }
+
+    #[test]
+    fn rejects_other_data_and_versions() -> Result<()> {
+        let registry = registry();
+        assert!(load(b"nope", &registry).is_err());
+
+        let mut bytes = save(&World::new(), &registry)?;
+        bytes[4] = 99;
+        let error = load(&bytes, &registry).err().unwrap();
+        assert!(error.to_string().contains("99"));
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_component_names_fail_to_load() -> Result<()> {
+        let mut world = World::new();
+        world.register_component::<Health>();
+        world.create_entity().with_component(Health(3))?;
+        let bytes = save(&world, &registry())?;
+
+        let error = load(&bytes, &SerializerRegistry::new()).err().unwrap();
+        assert!(error.to_string().contains("health"));
+        Ok(())
+    }
+
+    #[test]
+    fn entity_indices_are_bounded_by_the_slots() -> Result<()> {
+        let mut bytes = MAGIC.to_vec();
+        SNAPSHOT_VERSION.write(&mut bytes);
+        Vec::<String>::new().write(&mut bytes);
+        vec![0_u32].write(&mut bytes);
+        vec![(200_000_000_u32, Vec::<u8>::new())].write(&mut bytes);
+        Vec::<u8>::new().write(&mut bytes);
+
+        let error = load(&bytes, &registry()).err().unwrap();
+        assert!(error.to_string().contains("200000000"));
+
+        let mut huge = MAGIC.to_vec();
+        SNAPSHOT_VERSION.write(&mut huge);
+        Vec::<String>::new().write(&mut huge);
+        write_len(200_000_000, &mut huge);
+        assert!(load(&huge, &registry()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use eyre::Result;
+
+use crate::custom_errors::CustomErrors;
+
+/// How a value is laid out in a snapshot. Numbers are little endian, lengths are `u32`.
+pub trait Binary: Sized {
+    fn write(&self, out: &mut Vec<u8>);
+    fn read(reader: &mut Reader<'_>) -> Result<Self>;
+}
+
+/// Walks over the bytes of a snapshot, failing instead of reading past the end.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| {
+                CustomErrors::InvalidSnapshot(format!(
+                    "expected {len} more bytes at offset {}",
+                    self.position
+                ))
+            })?;
+
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn read<T: Binary>(&mut self) -> Result<T> {
+        T::read(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+}
+
+macro_rules! impl_binary_for_number {
+    ($($number:ty),*) => {
+        $(
+            impl Binary for $number {
+                fn write(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read(reader: &mut Reader<'_>) -> Result<Self> {
+                    let bytes = reader.take(std::mem::size_of::<$number>())?;
+                    Ok(<$number>::from_le_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_for_number!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl Binary for bool {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn read(reader: &mut Reader<'_>) -> Result<Self> {
+        match reader.read::<u8>()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(CustomErrors::InvalidSnapshot(format!("{byte} is not a bool")).into()),
+        }
+    }
+}
+
+impl Binary for usize {
+    fn write(&self, out: &mut Vec<u8>) {
+        (*self as u64).write(out);
+    }
+
+    fn read(reader: &mut Reader<'_>) -> Result<Self> {
+        let value = reader.read::<u64>()?;
+        usize::try_from(value).map_err(|_| {
+            CustomErrors::InvalidSnapshot(format!("{value} doesn't fit into a usize")).into()
+        })
+    }
+}
+
+impl Binary for String {
+    fn write(&self, out: &mut Vec<u8>) {
+        write_len(self.len(), out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn read(reader: &mut Reader<'_>) -> Result<Self> {
+        let len = read_len(reader)?;
+        let bytes = reader.take(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|error| CustomErrors::InvalidSnapshot(error.to_string()).into())
+    }
+}
+
+impl<T: Binary> Binary for Vec<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        write_len(self.len(), out);
+        self.iter().for_each(|value| value.write(out));
+    }
+
+    fn read(reader: &mut Reader<'_>) -> Result<Self> {
+        let len = read_len(reader)?;
+        (0..len).map(|_| reader.read()).collect()
+    }
+}
+
+impl<T: Binary> Binary for Option<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.is_some().write(out);
+        if let Some(value) = self {
+            value.write(out);
+        }
+    }
+
+    fn read(reader: &mut Reader<'_>) -> Result<Self> {
+        if reader.read::<bool>()? {
+            Ok(Some(reader.read()?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+macro_rules! impl_binary_for_tuple {
+    ($($value:ident),*) => {
+        impl<$($value: Binary),*> Binary for ($($value,)*) {
+            #[allow(non_snake_case, unused_variables)]
+            fn write(&self, out: &mut Vec<u8>) {
+                let ($($value,)*) = self;
+                $($value.write(out);)*
+            }
+
+            #[allow(unused_variables, clippy::unused_unit)]
+            fn read(reader: &mut Reader<'_>) -> Result<Self> {
+                Ok(($(reader.read::<$value>()?,)*))
+            }
+        }
+    };
+}
+
+impl_binary_for_tuple!();
+impl_binary_for_tuple!(A);
+impl_binary_for_tuple!(A, B);
+impl_binary_for_tuple!(A, B, C);
+impl_binary_for_tuple!(A, B, C, D);
+
+pub(crate) fn write_len(len: usize, out: &mut Vec<u8>) {
+    (len as u32).write(out);
+}
+
+pub(crate) fn read_len(reader: &mut Reader<'_>) -> Result<usize> {
+    Ok(reader.read::<u32>()? as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_round_trip() -> Result<()> {
+        let value = (-3_i16, 1.5_f32, "ship".to_owned(), vec![Some(true), None]);
+        let mut out = vec![];
+        value.write(&mut out);
+
+        let mut reader = Reader::new(&out);
+        assert_eq!(
+            reader.read::<(i16, f32, String, Vec<Option<bool>>)>()?,
+            value
+        );
+        assert!(reader.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn reading_past_the_end_fails() {
+        let mut reader = Reader::new(&[1, 0]);
+        assert!(reader.read::<u32>().is_err());
+    }
+}
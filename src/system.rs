@@ -0,0 +1,140 @@
+pub mod access;
+pub mod function_system;
+pub mod system_param;
+
+use std::{any::type_name, marker::PhantomData};
+
+use eyre::Result;
+
+use crate::World;
+
+use self::{access::Access, system_param::SystemView};
+
+/// A unit of game logic the `Schedule` runs against the `World`.
+pub trait System: Send {
+    fn name(&self) -> &str;
+    fn run(&mut self, world: &mut World) -> Result<()>;
+
+    /// The components and resources the system borrows. `None` means it needs the whole world
+    /// to itself and never runs alongside other systems.
+    fn access(&self) -> Option<&Access> {
+        None
+    }
+
+    /// Runs against a slice of the world handed out by a parallel executor. Only called on
+    /// systems that report an `access`.
+    fn run_in_view(&mut self, _view: SystemView<'_>) -> Result<()> {
+        Err(eyre::eyre!(
+            "{} needs exclusive access to the world",
+            self.name()
+        ))
+    }
+
+    /// Applies whatever the system deferred, like queued `Commands`. `run` already does this, a
+    /// parallel executor calls it for every system in a batch once the whole batch is done.
+    fn apply_deferred(&mut self, _world: &mut World) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Anything that can be turned into a boxed `System`. `Marker` only exists to keep the blanket
+/// implementations for different function shapes apart.
+pub trait IntoSystem<Marker> {
+    type System: System + 'static;
+
+    fn into_system(self) -> Result<Self::System>;
+}
+
+/// Lets systems either return nothing or a `Result`.
+pub trait SystemOutput {
+    fn into_result(self) -> Result<()>;
+}
+
+impl SystemOutput for () {
+    fn into_result(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl SystemOutput for Result<()> {
+    fn into_result(self) -> Result<()> {
+        self
+    }
+}
+
+/// A plain function that takes the whole `World`, like `fn spawn_wave(world: &mut World)`.
+pub struct ExclusiveSystem<F, Out> {
+    name: String,
+    function: F,
+    output: PhantomData<fn() -> Out>,
+}
+
+impl<F, Out> System for ExclusiveSystem<F, Out>
+where
+    F: FnMut(&mut World) -> Out + Send,
+    Out: SystemOutput,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Also applies whatever component hooks queued while the function had the world.
+    fn run(&mut self, world: &mut World) -> Result<()> {
+        (self.function)(world).into_result()?;
+        world.flush()
+    }
+}
+
+pub struct ExclusiveSystemMarker;
+
+impl<F, Out> IntoSystem<(ExclusiveSystemMarker, Out)> for F
+where
+    F: FnMut(&mut World) -> Out + Send + 'static,
+    Out: SystemOutput + 'static,
+{
+    type System = ExclusiveSystem<F, Out>;
+
+    fn into_system(self) -> Result<Self::System> {
+        Ok(ExclusiveSystem {
+            name: type_name::<F>().to_owned(),
+            function: self,
+            output: PhantomData,
+        })
+    }
+}
+
+impl<S: System + 'static> IntoSystem<()> for S {
+    type System = S;
+
+    fn into_system(self) -> Result<Self::System> {
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_up(world: &mut World) {
+        *world.get_resource_mut::<u32>().unwrap() += 1;
+    }
+
+    fn fail(_world: &mut World) -> Result<()> {
+        Err(eyre::eyre!("nope"))
+    }
+
+    #[test]
+    fn plain_functions_become_systems() -> Result<()> {
+        let mut world = World::new();
+        world.add_resource(0_u32);
+
+        let mut system = count_up.into_system()?;
+        system.run(&mut world)?;
+        system.run(&mut world)?;
+
+        assert_eq!(*world.get_resource::<u32>().unwrap(), 2);
+        assert!(system.name().ends_with("count_up"));
+        assert!(fail.into_system()?.run(&mut world).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use std::{any::TypeId, collections::HashMap};
+
+use eyre::Result;
+
+use crate::custom_errors::CustomErrors;
+
+/// The components and resources something reads and writes, keyed by type with the type name
+/// kept around for error messages.
+#[derive(Debug, Default, Clone)]
+pub struct Access {
+    component_reads: HashMap<TypeId, &'static str>,
+    component_writes: HashMap<TypeId, &'static str>,
+    resource_reads: HashMap<TypeId, &'static str>,
+    resource_writes: HashMap<TypeId, &'static str>,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_component_read(&mut self, type_id: TypeId, name: &'static str) -> Result<()> {
+        Self::add_read(
+            &mut self.component_reads,
+            &self.component_writes,
+            type_id,
+            name,
+        )
+    }
+
+    pub fn add_component_write(&mut self, type_id: TypeId, name: &'static str) -> Result<()> {
+        Self::add_write(
+            &self.component_reads,
+            &mut self.component_writes,
+            type_id,
+            name,
+        )
+    }
+
+    pub fn add_resource_read(&mut self, type_id: TypeId, name: &'static str) -> Result<()> {
+        Self::add_read(
+            &mut self.resource_reads,
+            &self.resource_writes,
+            type_id,
+            name,
+        )
+    }
+
+    pub fn add_resource_write(&mut self, type_id: TypeId, name: &'static str) -> Result<()> {
+        Self::add_write(
+            &self.resource_reads,
+            &mut self.resource_writes,
+            type_id,
+            name,
+        )
+    }
+
+    pub fn reads_resource(&self, type_id: &TypeId) -> bool {
+        self.resource_reads.contains_key(type_id)
+    }
+
+    pub fn writes_resource(&self, type_id: &TypeId) -> bool {
+        self.resource_writes.contains_key(type_id)
+    }
+
+    /// Whether two systems with these accesses can run at the same time.
+    pub fn is_compatible(&self, other: &Access) -> bool {
+        !Self::overlaps(
+            &self.component_writes,
+            &other.component_reads,
+            &other.component_writes,
+        ) && !Self::overlaps(
+            &other.component_writes,
+            &self.component_reads,
+            &self.component_writes,
+        ) && !Self::overlaps(
+            &self.resource_writes,
+            &other.resource_reads,
+            &other.resource_writes,
+        ) && !Self::overlaps(
+            &other.resource_writes,
+            &self.resource_reads,
+            &self.resource_writes,
+        )
+    }
+
+    fn overlaps(
+        writes: &HashMap<TypeId, &'static str>,
+        reads: &HashMap<TypeId, &'static str>,
+        other_writes: &HashMap<TypeId, &'static str>,
+    ) -> bool {
+        writes
+            .keys()
+            .any(|type_id| reads.contains_key(type_id) || other_writes.contains_key(type_id))
+    }
+
+    fn add_read(
+        reads: &mut HashMap<TypeId, &'static str>,
+        writes: &HashMap<TypeId, &'static str>,
+        type_id: TypeId,
+        name: &'static str,
+    ) -> Result<()> {
+        if writes.contains_key(&type_id) {
+            return Err(CustomErrors::ConflictingAccess(name.to_owned()).into());
+        }
+        reads.insert(type_id, name);
+        Ok(())
+    }
+
+    fn add_write(
+        reads: &HashMap<TypeId, &'static str>,
+        writes: &mut HashMap<TypeId, &'static str>,
+        type_id: TypeId,
+        name: &'static str,
+    ) -> Result<()> {
+        if reads.contains_key(&type_id) || writes.contains_key(&type_id) {
+            return Err(CustomErrors::ConflictingAccess(name.to_owned()).into());
+        }
+        writes.insert(type_id, name);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_reads_are_fine() -> Result<()> {
+        let mut access = Access::new();
+        access.add_component_read(TypeId::of::<u32>(), "u32")?;
+        access.add_component_read(TypeId::of::<u32>(), "u32")?;
+        access.add_resource_write(TypeId::of::<u32>(), "u32")?;
+        Ok(())
+    }
+
+    #[test]
+    fn writes_conflict_with_anything_of_the_same_type() {
+        let mut access = Access::new();
+        access
+            .add_component_write(TypeId::of::<u32>(), "u32")
+            .unwrap();
+
+        let error = access
+            .add_component_read(TypeId::of::<u32>(), "u32")
+            .unwrap_err();
+        assert!(error.to_string().contains("u32"));
+        assert!(access
+            .add_component_write(TypeId::of::<u32>(), "u32")
+            .is_err());
+
+        access
+            .add_resource_read(TypeId::of::<f32>(), "f32")
+            .unwrap();
+        assert!(access
+            .add_resource_write(TypeId::of::<f32>(), "f32")
+            .is_err());
+    }
+
+    #[test]
+    fn compatibility_between_accesses() -> Result<()> {
+        let mut reads_u32 = Access::new();
+        reads_u32.add_component_read(TypeId::of::<u32>(), "u32")?;
+        let mut also_reads_u32 = Access::new();
+        also_reads_u32.add_component_read(TypeId::of::<u32>(), "u32")?;
+        also_reads_u32.add_resource_write(TypeId::of::<f32>(), "f32")?;
+        let mut writes_u32 = Access::new();
+        writes_u32.add_component_write(TypeId::of::<u32>(), "u32")?;
+        let mut reads_f32 = Access::new();
+        reads_f32.add_resource_read(TypeId::of::<f32>(), "f32")?;
+
+        assert!(reads_u32.is_compatible(&also_reads_u32));
+        assert!(!reads_u32.is_compatible(&writes_u32));
+        assert!(!writes_u32.is_compatible(&reads_u32));
+        assert!(!reads_f32.is_compatible(&also_reads_u32));
+        assert!(reads_f32.is_compatible(&writes_u32));
+        Ok(())
+    }
+}
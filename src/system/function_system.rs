@@ -0,0 +1,187 @@
+use std::{any::type_name, marker::PhantomData};
+
+use eyre::Result;
+
+use crate::World;
+
+use super::{
+    access::Access,
+    system_param::{SystemParam, SystemParamItem, SystemView},
+    IntoSystem, System, SystemOutput,
+};
+
+/// A function whose every argument is a `SystemParam`, like
+/// `fn movement(time: Res<Time>, q: Query<(&mut Pos, &Vel)>)`.
+pub trait SystemParamFunction<Marker>: Send + 'static {
+    type Param: SystemParam;
+    type Out: SystemOutput;
+
+    fn run(&mut self, params: SystemParamItem<'_, '_, Self::Param>) -> Self::Out;
+}
+
+macro_rules! impl_system_param_function {
+    ($($param:ident),*) => {
+        impl<Func, Out, $($param: SystemParam),*> SystemParamFunction<fn($($param,)*) -> Out>
+            for Func
+        where
+            Func: Send + 'static,
+            for<'a> &'a mut Func:
+                FnMut($($param),*) -> Out + FnMut($(SystemParamItem<$param>),*) -> Out,
+            Out: SystemOutput,
+        {
+            type Param = ($($param,)*);
+            type Out = Out;
+
+            #[allow(non_snake_case)]
+            fn run(&mut self, params: SystemParamItem<'_, '_, Self::Param>) -> Out {
+                // Naming the parameter types explicitly is what lets the compiler pick the
+                // `FnMut(Item)` implementation instead of `FnMut(Param)`.
+                #[allow(clippy::too_many_arguments)]
+                fn call_inner<Out, $($param),*>(
+                    mut function: impl FnMut($($param),*) -> Out,
+                    $($param: $param,)*
+                ) -> Out {
+                    function($($param),*)
+                }
+
+                let ($($param,)*) = params;
+                call_inner(self, $($param),*)
+            }
+        }
+    };
+}
+
+impl_system_param_function!();
+impl_system_param_function!(A);
+impl_system_param_function!(A, B);
+impl_system_param_function!(A, B, C);
+impl_system_param_function!(A, B, C, D);
+impl_system_param_function!(A, B, C, D, E);
+impl_system_param_function!(A, B, C, D, E, F);
+impl_system_param_function!(A, B, C, D, E, F, G);
+impl_system_param_function!(A, B, C, D, E, F, G, H);
+
+pub struct FunctionSystem<Marker, F: SystemParamFunction<Marker>> {
+    name: String,
+    function: F,
+    state: <F::Param as SystemParam>::State,
+    access: Access,
+    last_run: Option<u32>,
+    marker: PhantomData<fn() -> Marker>,
+}
+
+impl<Marker: 'static, F: SystemParamFunction<Marker>> System for FunctionSystem<Marker, F> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&mut self, world: &mut World) -> Result<()> {
+        let view = SystemView::new(world, &self.access);
+        self.run_in_view(view)?;
+        self.apply_deferred(world)
+    }
+
+    fn access(&self) -> Option<&Access> {
+        Some(&self.access)
+    }
+
+    fn run_in_view(&mut self, mut view: SystemView<'_>) -> Result<()> {
+        let this_run = view.entities().change_tick();
+        view.set_last_run(self.last_run);
+        let params = F::Param::fetch(&mut self.state, &mut view)?;
+        let output = self.function.run(params).into_result();
+
+        self.last_run = Some(this_run);
+        output
+    }
+
+    fn apply_deferred(&mut self, world: &mut World) -> Result<()> {
+        F::Param::apply(&mut self.state, world)
+    }
+}
+
+pub struct FunctionSystemMarker;
+
+impl<Marker: 'static, F: SystemParamFunction<Marker>> IntoSystem<(FunctionSystemMarker, Marker)>
+    for F
+{
+    type System = FunctionSystem<Marker, F>;
+
+    /// Fails when two parameters borrow the same component or resource and one of them does so
+    /// mutably.
+    fn into_system(self) -> Result<Self::System> {
+        let mut access = Access::new();
+        F::Param::access(&mut access)?;
+
+        Ok(FunctionSystem {
+            name: type_name::<F>().to_owned(),
+            function: self,
+            state: Default::default(),
+            access,
+            last_run: None,
+            marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{entities::query::Query, Res, ResMut};
+
+    use super::*;
+
+    struct Time(f32);
+    struct Score(u32);
+    struct Position(f32);
+    struct Velocity(f32);
+
+    fn movement(time: Res<Time>, query: Query<(&mut Position, &Velocity)>) {
+        for (_entity, mut position, velocity) in query.iter() {
+            position.0 += velocity.0 * time.0;
+        }
+    }
+
+    fn scoring(mut score: ResMut<Score>, time: Res<Time>) -> Result<()> {
+        score.0 += time.0 as u32;
+        Ok(())
+    }
+
+    fn aliasing(_first: Query<&mut Position>, _second: Query<&Position>) {}
+
+    fn double_resource(_first: ResMut<Score>, _second: Res<Score>) {}
+
+    #[test]
+    fn parameters_are_fetched_from_the_world() -> Result<()> {
+        let mut world = World::new();
+        world.add_resource(Time(2.0));
+        world.add_resource(Score(0));
+        world.register_component::<Position>();
+        world.register_component::<Velocity>();
+        world
+            .create_entity()
+            .with_component(Position(1.0))?
+            .with_component(Velocity(3.0))?;
+
+        movement.into_system()?.run(&mut world)?;
+        scoring.into_system()?.run(&mut world)?;
+
+        let (_entity, position) = world.query::<&Position>()?.iter().next().unwrap();
+        assert_eq!(position.0, 7.0);
+        assert_eq!(world.get_resource::<Score>().unwrap().0, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn conflicting_parameters_fail_to_build() {
+        assert!(aliasing.into_system().is_err());
+        assert!(double_resource.into_system().is_err());
+    }
+
+    #[test]
+    fn missing_resource_fails_the_run() -> Result<()> {
+        let mut world = World::new();
+        let mut system = scoring.into_system()?;
+        assert!(system.run(&mut world).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+};
+
+use eyre::Result;
+
+use crate::{
+    custom_errors::CustomErrors,
+    entities::{
+        query::{query_data::QueryData, Query},
+        Entities,
+    },
+    World,
+};
+
+use super::access::Access;
+
+/// The part of the `World` a system's parameters get fetched from. Only the resources the
+/// system declared are split out of the world, each as its own borrow, so a `ResMut` and a
+/// `Res` of different types can be handed out side by side.
+pub struct SystemView<'w> {
+    entities: &'w Entities,
+    resources: HashMap<TypeId, ResourceSlot<'w>>,
+    last_run: Option<u32>,
+}
+
+enum ResourceSlot<'w> {
+    Unique(&'w mut Box<dyn Any + Send + Sync>),
+    Shared(&'w Box<dyn Any + Send + Sync>),
+}
+
+impl<'w> SystemView<'w> {
+    pub fn new(world: &'w mut World, access: &Access) -> Self {
+        Self::split(world, &[access]).pop().unwrap()
+    }
+
+    /// One view per access, in the same order. A resource written by one of them goes to that
+    /// view alone, a resource only read is shared between every view reading it; the accesses
+    /// are expected not to conflict with each other.
+    pub fn split(world: &'w mut World, accesses: &[&Access]) -> Vec<Self> {
+        let mut views: Vec<Self> = accesses
+            .iter()
+            .map(|_access| Self {
+                entities: &world.entities,
+                resources: HashMap::new(),
+                last_run: None,
+            })
+            .collect();
+
+        for (type_id, data) in world.resources.slots_mut() {
+            if let Some(writer) = accesses
+                .iter()
+                .position(|access| access.writes_resource(&type_id))
+            {
+                views[writer]
+                    .resources
+                    .insert(type_id, ResourceSlot::Unique(data));
+                continue;
+            }
+
+            let data = &*data;
+            for (view, access) in views.iter_mut().zip(accesses) {
+                if access.reads_resource(&type_id) {
+                    view.resources.insert(type_id, ResourceSlot::Shared(data));
+                }
+            }
+        }
+
+        views
+    }
+
+    pub fn entities(&self) -> &'w Entities {
+        self.entities
+    }
+
+    /// The change tick the system last ran at, `None` on its first run.
+    pub fn last_run(&self) -> Option<u32> {
+        self.last_run
+    }
+
+    pub fn set_last_run(&mut self, last_run: Option<u32>) {
+        self.last_run = last_run;
+    }
+
+    pub fn resource<T: Any>(&mut self) -> Result<&'w T> {
+        let type_id = TypeId::of::<T>();
+        let data = match self.resources.remove(&type_id) {
+            Some(ResourceSlot::Unique(data)) => &*data,
+            Some(ResourceSlot::Shared(data)) => data,
+            None => return Err(CustomErrors::ResourceDoesNotExists.into()),
+        };
+
+        self.resources.insert(type_id, ResourceSlot::Shared(data));
+        data.downcast_ref::<T>()
+            .ok_or(CustomErrors::DowncastsToWrongType.into())
+    }
+
+    pub fn resource_mut<T: Any>(&mut self) -> Result<&'w mut T> {
+        match self.resources.remove(&TypeId::of::<T>()) {
+            Some(ResourceSlot::Unique(data)) => data
+                .downcast_mut::<T>()
+                .ok_or(CustomErrors::DowncastsToWrongType.into()),
+            Some(ResourceSlot::Shared(_data)) => {
+                Err(CustomErrors::ConflictingAccess(type_name::<T>().to_owned()).into())
+            }
+            None => Err(CustomErrors::ResourceDoesNotExists.into()),
+        }
+    }
+}
+
+/// Something a system can ask for in its signature. `State` lives in the system between runs,
+/// `Item` is what the function actually receives.
+pub trait SystemParam {
+    type State: Default + Send;
+    type Item<'w, 's>;
+
+    fn access(access: &mut Access) -> Result<()>;
+
+    fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        view: &mut SystemView<'w>,
+    ) -> Result<Self::Item<'w, 's>>;
+
+    /// Called with exclusive access to the world once the system has finished, for parameters
+    /// that defer their work, like `Commands`.
+    fn apply(_state: &mut Self::State, _world: &mut World) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub type SystemParamItem<'w, 's, P> = <P as SystemParam>::Item<'w, 's>;
+
+/// Shared access to a resource, `Res<Time>` in a system's signature.
+pub struct Res<'w, T> {
+    value: &'w T,
+}
+
+impl<T> Deref for Res<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T: Any> SystemParam for Res<'_, T> {
+    type State = ();
+    type Item<'w, 's> = Res<'w, T>;
+
+    fn access(access: &mut Access) -> Result<()> {
+        access.add_resource_read(TypeId::of::<T>(), type_name::<T>())
+    }
+
+    fn fetch<'w, 's>(
+        _state: &'s mut Self::State,
+        view: &mut SystemView<'w>,
+    ) -> Result<Self::Item<'w, 's>> {
+        Ok(Res {
+            value: view.resource::<T>()?,
+        })
+    }
+}
+
+/// Exclusive access to a resource, `ResMut<Score>` in a system's signature.
+pub struct ResMut<'w, T> {
+    value: &'w mut T,
+}
+
+impl<T> Deref for ResMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T> DerefMut for ResMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value
+    }
+}
+
+impl<T: Any> SystemParam for ResMut<'_, T> {
+    type State = ();
+    type Item<'w, 's> = ResMut<'w, T>;
+
+    fn access(access: &mut Access) -> Result<()> {
+        access.add_resource_write(TypeId::of::<T>(), type_name::<T>())
+    }
+
+    fn fetch<'w, 's>(
+        _state: &'s mut Self::State,
+        view: &mut SystemView<'w>,
+    ) -> Result<Self::Item<'w, 's>> {
+        Ok(ResMut {
+            value: view.resource_mut::<T>()?,
+        })
+    }
+}
+
+/// `Added` and `Changed` filters on a query parameter compare against the system's last run.
+impl<D: QueryData> SystemParam for Query<'_, D> {
+    type State = ();
+    type Item<'w, 's> = Query<'w, D>;
+
+    fn access(access: &mut Access) -> Result<()> {
+        D::access(access)
+    }
+
+    fn fetch<'w, 's>(
+        _state: &'s mut Self::State,
+        view: &mut SystemView<'w>,
+    ) -> Result<Self::Item<'w, 's>> {
+        let mut query = Query::typed(view.entities())?;
+        if let Some(last_run) = view.last_run() {
+            query.since(last_run);
+        }
+        Ok(query)
+    }
+}
+
+macro_rules! impl_system_param_for_tuple {
+    ($($param:ident),*) => {
+        impl<$($param: SystemParam),*> SystemParam for ($($param,)*) {
+            type State = ($($param::State,)*);
+            type Item<'w, 's> = ($($param::Item<'w, 's>,)*);
+
+            #[allow(unused_variables)]
+            fn access(access: &mut Access) -> Result<()> {
+                $($param::access(access)?;)*
+                Ok(())
+            }
+
+            #[allow(non_snake_case, unused_variables, clippy::unused_unit)]
+            fn fetch<'w, 's>(
+                state: &'s mut Self::State,
+                view: &mut SystemView<'w>,
+            ) -> Result<Self::Item<'w, 's>> {
+                let ($($param,)*) = state;
+                Ok(($($param::fetch($param, view)?,)*))
+            }
+
+            #[allow(non_snake_case, unused_variables)]
+            fn apply(state: &mut Self::State, world: &mut World) -> Result<()> {
+                let ($($param,)*) = state;
+                $($param::apply($param, world)?;)*
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_system_param_for_tuple!();
+impl_system_param_for_tuple!(A);
+impl_system_param_for_tuple!(A, B);
+impl_system_param_for_tuple!(A, B, C);
+impl_system_param_for_tuple!(A, B, C, D);
+impl_system_param_for_tuple!(A, B, C, D, E);
+impl_system_param_for_tuple!(A, B, C, D, E, F);
+impl_system_param_for_tuple!(A, B, C, D, E, F, G);
+impl_system_param_for_tuple!(A, B, C, D, E, F, G, H);
@@ -1,4 +1,4 @@
-use std::{any::Any, cell::RefCell, rc::Rc};
+use std::{any::TypeId, thread};
 
 use ecs_in_rust::*;
 use eyre::Result;
@@ -39,30 +39,30 @@ fn query_for_entities() -> Result<()> {
         .with_component(Size(12.0))?;
 
     let query = world
-        .query()
+        .query::<()>()?
         .with_component::<Location>()?
         .with_component::<Size>()?
         .run();
 
-    let locations: &Vec<Rc<RefCell<dyn Any>>> = &query.1[0];
-    let sizes: &Vec<Rc<RefCell<dyn Any>>> = &query.1[1];
+    let locations = &query.1[0];
+    let sizes = &query.1[1];
 
     assert_eq!(locations.len(), sizes.len());
     assert_eq!(locations.len(), 2);
 
-    let borrowed_first = locations[0].borrow();
+    let borrowed_first = locations[0].read().unwrap();
     let first_location = borrowed_first.downcast_ref::<Location>().unwrap();
     assert_eq!(first_location.0, 42.0);
 
-    let borrowed_first_size = sizes[0].borrow();
+    let borrowed_first_size = sizes[0].read().unwrap();
     let first_size = borrowed_first_size.downcast_ref::<Size>().unwrap();
     assert_eq!(first_size.0, 10.0);
 
-    let borrowed_second = locations[1].borrow();
+    let borrowed_second = locations[1].read().unwrap();
     let second_location = borrowed_second.downcast_ref::<Location>().unwrap();
     assert_eq!(second_location.0, 44.0);
 
-    let mut borrowed_second_size = sizes[1].borrow_mut();
+    let mut borrowed_second_size = sizes[1].write().unwrap();
     let second_size = borrowed_second_size.downcast_mut::<Size>().unwrap();
     second_size.0 += 1.0;
     assert_eq!(second_size.0, 13.0);
@@ -71,32 +71,66 @@ fn query_for_entities() -> Result<()> {
 }
 
 #[test]
-fn delete_component_to_entity() -> Result<()> {
+#[allow(clippy::float_cmp)]
+fn typed_query_for_entities() -> Result<()> {
     let mut world = World::new();
-
     world.register_component::<Location>();
     world.register_component::<Size>();
 
     world
         .create_entity()
-        .with_component(Location(0.0, 0.0))?
+        .with_component(Location(42.0, 24.0))?
         .with_component(Size(10.0))?;
 
+    world.create_entity().with_component(Size(11.0))?;
+
+    world.create_entity().with_component(Location(43.0, 25.0))?;
+
     world
+        .create_entity()
+        .with_component(Location(44.0, 26.0))?
+        .with_component(Size(12.0))?;
+
+    let mut found = vec![];
+    for (entity, location, mut size) in world.query::<(&Location, &mut Size)>()?.iter() {
+        size.0 += 1.0;
+        found.push((entity.index, location.0, location.1, size.0));
+    }
+
+    assert_eq!(found, vec![(0, 42.0, 24.0, 11.0), (3, 44.0, 26.0, 13.0)]);
+
+    Ok(())
+}
+
+#[test]
+fn delete_component_to_entity() -> Result<()> {
+    let mut world = World::new();
+
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+
+    let first = world
+        .create_entity()
+        .with_component(Location(0.0, 0.0))?
+        .with_component(Size(10.0))?
+        .entity();
+
+    let second = world
         .create_entity()
         .with_component(Location(29.0, 0.0))?
-        .with_component(Size(15.0))?;
+        .with_component(Size(15.0))?
+        .entity();
 
-    world.delete_component_by_entity_id::<Location>(0)?;
+    world.delete_component_by_entity_id::<Location>(first)?;
 
     let query = world
-        .query()
+        .query::<()>()?
         .with_component::<Location>()?
         .with_component::<Size>()?
         .run();
 
     assert_eq!(query.0.len(), 1);
-    assert_eq!(query.0[0], 1);
+    assert_eq!(query.0[0], second);
     Ok(())
 }
 
@@ -107,12 +141,15 @@ fn add_component_to_entity() -> Result<()> {
     world.register_component::<Location>();
     world.register_component::<Size>();
 
-    world.create_entity().with_component(Location(0.0, 0.0))?;
+    let entity = world
+        .create_entity()
+        .with_component(Location(0.0, 0.0))?
+        .entity();
 
-    world.add_component_to_entity_by_id(Size(20.0), 0)?;
+    world.add_component_to_entity_by_id(Size(20.0), entity)?;
 
     let query = world
-        .query()
+        .query::<()>()?
         .with_component::<Location>()?
         .with_component::<Size>()?
         .run();
@@ -128,32 +165,33 @@ fn deleting_on_entity() -> Result<()> {
     world.register_component::<Location>();
     world.register_component::<Size>();
 
-    world
+    let first = world
         .create_entity()
         .with_component(Location(0.0, 0.0))?
-        .with_component(Size(10.0))?;
+        .with_component(Size(10.0))?
+        .entity();
 
     world
         .create_entity()
         .with_component(Location(29.0, 0.0))?
         .with_component(Size(15.0))?;
 
-    world.delete_entity_by_id(0)?;
+    world.delete_entity_by_id(first)?;
 
-    let query = world.query().with_component::<Location>()?.run();
+    let query = world.query::<()>()?.with_component::<Location>()?.run();
 
     assert_eq!(query.0.len(), 1);
 
-    let borrowed_location = query.1[0][0].borrow();
+    let borrowed_location = query.1[0][0].read().unwrap();
     let location = borrowed_location.downcast_ref::<Location>().unwrap();
 
     assert_eq!(location.0, 29.0);
 
     world.create_entity().with_component(Location(30.0, 50.0))?;
 
-    let query = world.query().with_component::<Location>()?.run();
+    let query = world.query::<()>()?.with_component::<Location>()?.run();
 
-    let borrowed_location = query.1[0][0].borrow();
+    let borrowed_location = query.1[0][0].read().unwrap();
     let location = borrowed_location.downcast_ref::<Location>().unwrap();
 
     assert_eq!(location.0, 30.0);
@@ -161,5 +199,407 @@ fn deleting_on_entity() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn stale_handles_are_rejected() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+
+    let stale = world
+        .create_entity()
+        .with_component(Location(0.0, 0.0))?
+        .entity();
+    world.delete_entity_by_id(stale)?;
+
+    let current = world
+        .create_entity()
+        .with_component(Location(1.0, 1.0))?
+        .entity();
+
+    assert_eq!(stale.index, current.index);
+    assert!(!world.is_alive(stale));
+    assert!(world.is_alive(current));
+    assert!(world
+        .add_component_to_entity_by_id(Size(5.0), stale)
+        .is_err());
+    assert!(world
+        .delete_component_by_entity_id::<Location>(stale)
+        .is_err());
+    assert!(world.delete_entity_by_id(stale).is_err());
+
+    let query = world.query::<()>()?.with_component::<Size>()?.run();
+    assert!(query.0.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn query_without_component() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Health>();
+    world.register_component::<Dead>();
+
+    world.create_entity().with_component(Health(10))?;
+    world
+        .create_entity()
+        .with_component(Health(0))?
+        .with_component(Dead)?;
+    world.create_entity().with_component(Health(5))?;
+
+    let mut healths = vec![];
+    for (_entity, health) in world
+        .query::<&Health>()?
+        .without_component::<Dead>()?
+        .iter()
+    {
+        healths.push(health.0);
+    }
+
+    assert_eq!(healths, vec![10, 5]);
+    Ok(())
+}
+
+#[test]
+fn query_with_optional_component() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Sprite>();
+
+    world
+        .create_entity()
+        .with_component(Location(1.0, 1.0))?
+        .with_component(Sprite("ship"))?;
+    world.create_entity().with_component(Location(2.0, 2.0))?;
+    world.create_entity().with_component(Sprite("orphan"))?;
+
+    let sprites: Vec<Option<&str>> = world
+        .query::<(&Location, Option<&Sprite>)>()?
+        .iter()
+        .map(|(_entity, _location, sprite)| sprite.map(|sprite| sprite.0))
+        .collect();
+
+    assert_eq!(sprites, vec![Some("ship"), None]);
+    Ok(())
+}
+
+#[test]
+fn query_for_changed_components() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+
+    for x in 0..3 {
+        world
+            .create_entity()
+            .with_component(Location(x as f32, 0.0))?
+            .with_component(Size(1.0))?;
+    }
+
+    let last_run = world.increment_change_tick();
+    world.increment_change_tick();
+
+    for (_entity, mut location, _size) in world.query::<(&mut Location, &Size)>()?.iter() {
+        location.1 += 1.0;
+    }
+    let late = world
+        .create_entity()
+        .with_component(Location(9.0, 9.0))?
+        .entity();
+
+    let mut moved = world.query::<&Location>()?;
+    moved.filter::<Changed<Location>>()?.since(last_run);
+    assert_eq!(moved.iter().count(), 4);
+
+    let mut added = world.query::<()>()?;
+    added.filter::<Added<Location>>()?.since(last_run);
+    assert_eq!(added.iter().collect::<Vec<_>>(), vec![late]);
+
+    let mut resized = world.query::<()>()?;
+    resized.filter::<Changed<Size>>()?.since(last_run);
+    assert_eq!(resized.iter().count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn mutable_borrows_without_writes_are_not_changes() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    let entities = world.spawn_batch((0..3).map(|x| (Location(x as f32, 0.0),)))?;
+
+    let last_run = world.increment_change_tick();
+    world.increment_change_tick();
+
+    for (_entity, mut location) in world.query::<&mut Location>()?.iter() {
+        if location.0 == 1.0 {
+            location.1 = 5.0;
+        }
+    }
+    let untouched = world
+        .query::<()>()?
+        .with_component::<Location>()?
+        .run_entity();
+    untouched[2].get_component_mut::<Location>()?;
+
+    let mut moved = world.query::<()>()?;
+    moved.filter::<Changed<Location>>()?.since(last_run);
+    assert_eq!(moved.iter().collect::<Vec<_>>(), vec![entities[1]]);
+    Ok(())
+}
+
+#[test]
+fn removed_components_are_reported_once_per_reader() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+
+    let mut physics = RemovedComponents::<Location>::new();
+    let mut sound = RemovedComponents::<Location>::new();
+
+    let first = world
+        .create_entity()
+        .with_component(Location(0.0, 0.0))?
+        .with_component(Size(1.0))?
+        .entity();
+    let second = world
+        .create_entity()
+        .with_component(Location(1.0, 1.0))?
+        .entity();
+    let sizeless = world.create_entity().with_component(Size(2.0))?.entity();
+
+    world.delete_component_by_entity_id::<Location>(first)?;
+    world.delete_component_by_entity_id::<Location>(first)?;
+    assert_eq!(physics.read(&world), vec![first]);
+
+    world.delete_entity_by_id(second)?;
+    world.delete_entity_by_id(sizeless)?;
+    assert_eq!(physics.read(&world), vec![second]);
+    assert!(physics.read(&world).is_empty());
+    assert_eq!(sound.read(&world), vec![first, second]);
+
+    world.clear_removed_components();
+    world.delete_entity_by_id(first)?;
+    assert!(physics.read(&world).is_empty());
+    assert_eq!(RemovedComponents::<Size>::new().read(&world), vec![first]);
+
+    Ok(())
+}
+
+#[test]
+fn world_can_be_read_from_several_threads() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Health>();
+    world.add_resource(Sprite("shared"));
+
+    for health in 1..=10 {
+        world.create_entity().with_component(Health(health))?;
+    }
+
+    let totals = thread::scope(|scope| {
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    let total: u32 = world
+                        .query::<&Health>()
+                        .unwrap()
+                        .iter()
+                        .map(|(_entity, health)| health.0)
+                        .sum();
+                    (total, world.get_resource::<Sprite>().unwrap().0)
+                })
+            })
+            .collect();
+        readers
+            .into_iter()
+            .map(|reader| reader.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    assert_eq!(totals, vec![(55, "shared"); 4]);
+
+    let world = thread::spawn(move || world).join().unwrap();
+    assert_eq!(world.query::<&Health>()?.iter().count(), 10);
+    Ok(())
+}
+
+#[test]
+fn conflicting_component_borrows_fail() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Health>();
+    world.create_entity().with_component(Health(3))?;
+
+    let entities = world
+        .query::<()>()?
+        .with_component::<Health>()?
+        .run_entity();
+    let health = entities[0].get_component::<Health>()?;
+    let also_health = entities[0].get_component::<Health>()?;
+    assert!(entities[0].get_component_mut::<Health>().is_err());
+    assert_eq!(health.0 + also_health.0, 6);
+    drop((health, also_health));
+
+    entities[0].get_component_mut::<Health>()?.0 += 1;
+    assert_eq!(entities[0].get_component::<Health>()?.0, 4);
+    Ok(())
+}
+
+#[test]
+fn bundles_are_inserted_and_removed_together() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+    world.register_component::<Health>();
+
+    let ship = world.spawn((Location(1.0, 2.0), Size(3.0)))?;
+    world.insert_bundle(ship, (Health(10), Size(4.0)))?;
+    let sizes: Vec<(f32, u32)> = world
+        .query::<(&Size, &Health)>()?
+        .iter()
+        .map(|(_entity, size, health)| (size.0, health.0))
+        .collect();
+    assert_eq!(sizes, vec![(4.0, 10)]);
+
+    let mut removed = RemovedComponents::<Location>::new();
+    world.remove_bundle::<(Location, Health)>(ship)?;
+    assert_eq!(removed.read(&world), vec![ship]);
+    assert_eq!(world.query::<&Size>()?.iter().count(), 1);
+    assert_eq!(world.query::<&Health>()?.iter().count(), 0);
+
+    assert!(world.spawn((Health(1), Dead)).is_err());
+    assert!(world.insert_bundle(ship, (Health(1), Dead)).is_err());
+    assert_eq!(world.query::<()>()?.iter().count(), 1);
+    assert_eq!(world.query::<&Health>()?.iter().count(), 0);
+    Ok(())
+}
+
+#[test]
+fn spawning_in_batches() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Health>();
+    world.register_component::<Dead>();
+
+    let survivor = world.spawn((Health(0),))?;
+    let spawned =
+        world.spawn_batch((0..1000).map(|index| (Location(index as f32, 0.0), Health(index))))?;
+    assert_eq!(spawned.len(), 1000);
+    assert!(spawned.iter().all(|entity| world.is_alive(*entity)));
+
+    let healths: Vec<(Entity, u32)> = world
+        .query::<(&Health, &Location)>()?
+        .iter()
+        .map(|(entity, health, _location)| (entity, health.0))
+        .collect();
+    assert_eq!(healths.len(), 1000);
+    assert_eq!(healths[999], (spawned[999], 999));
+    assert_eq!(world.query::<&Health>()?.iter().next().unwrap().0, survivor);
+
+    assert!(world.spawn_batch([(Health(1), Size(1.0))]).is_err());
+    assert_eq!(world.query::<()>()?.iter().count(), 1001);
+    assert!(world.spawn_batch(Vec::<(Dead,)>::new())?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn bundles_that_break_their_declaration_are_rejected() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+    let ship = world.spawn((Location(1.0, 1.0),))?;
+
+    assert!(world.spawn(Swapped).is_err());
+    assert!(world.spawn(Short).is_err());
+    assert!(world.insert_bundle(ship, Swapped).is_err());
+    assert!(world.spawn_batch([Short, Short]).is_err());
+
+    assert_eq!(world.query::<()>()?.iter().count(), 1);
+    assert_eq!(world.query::<&Size>()?.iter().count(), 0);
+    assert_eq!(world.query::<&Location>()?.iter().next().unwrap().1 .0, 1.0);
+    Ok(())
+}
+
+#[test]
+fn registering_a_component_twice_is_harmless() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+    let located = world.spawn((Location(1.0, 1.0),))?;
+    world.register_component::<Location>();
+    world.register_component::<Health>();
+    world.spawn((Health(1),))?;
+
+    let locations: Vec<Entity> = world
+        .query::<&Location>()?
+        .iter()
+        .map(|(entity, _location)| entity)
+        .collect();
+    assert_eq!(locations, vec![located]);
+    Ok(())
+}
+
+#[test]
+fn overlapping_queries_report_the_conflict() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Health>();
+    world.spawn_batch([(Health(1),), (Health(2),)])?;
+
+    for (_entity, mut health) in world.query::<&mut Health>()?.iter() {
+        let readable = world
+            .query::<&Health>()?
+            .try_iter()
+            .filter(|item| item.is_ok())
+            .count();
+        assert_eq!(readable, 1);
+        health.0 += 1;
+    }
+
+    let total: u32 = world
+        .query::<&Health>()?
+        .try_iter()
+        .map(|item| item.map(|(_entity, health)| health.0))
+        .sum::<Result<u32>>()?;
+    assert_eq!(total, 5);
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn overlapping_query_iteration_panics() {
+    let mut world = World::new();
+    world.register_component::<Health>();
+    world.spawn((Health(1),)).unwrap();
+
+    for (_entity, _health) in world.query::<&mut Health>().unwrap().iter() {
+        world.query::<&Health>().unwrap().iter().count();
+    }
+}
+
 struct Location(pub f32, pub f32);
 struct Size(pub f32);
+struct Health(pub u32);
+struct Dead;
+struct Sprite(pub &'static str);
+/// Declares its components in the opposite order to the one it pushes them in.
+struct Swapped;
+/// Declares two components but only pushes one.
+struct Short;
+
+impl Bundle for Swapped {
+    fn component_ids(type_ids: &mut Vec<TypeId>) {
+        <(Location, Size)>::component_ids(type_ids);
+    }
+
+    fn write_components(self, writer: &mut BundleWriter) {
+        (Size(2.0), Location(2.0, 2.0)).write_components(writer);
+    }
+}
+
+impl Bundle for Short {
+    fn component_ids(type_ids: &mut Vec<TypeId>) {
+        <(Location, Size)>::component_ids(type_ids);
+    }
+
+    fn write_components(self, writer: &mut BundleWriter) {
+        writer.push(Location(3.0, 3.0));
+    }
+}
@@ -0,0 +1,113 @@
+use ecs_in_rust::*;
+use eyre::Result;
+
+#[test]
+fn despawning_a_ship_takes_its_parts_with_it() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Name>();
+
+    let fleet = spawn(&mut world, "fleet")?;
+    let ship = spawn(&mut world, "ship")?;
+    let turret = spawn(&mut world, "turret")?;
+    let barrel = spawn(&mut world, "barrel")?;
+    let thruster = spawn(&mut world, "thruster")?;
+    let escort = spawn(&mut world, "escort")?;
+
+    world.add_child(fleet, ship)?;
+    world.add_child(fleet, escort)?;
+    world.add_child(ship, turret)?;
+    world.add_child(ship, thruster)?;
+    world.add_child(turret, barrel)?;
+
+    let names = |entities: Vec<Entity>| -> Result<Vec<&str>> {
+        entities
+            .iter()
+            .map(|entity| name_of(&world, *entity))
+            .collect()
+    };
+    assert_eq!(
+        names(world.descendants_depth_first(fleet)?.collect())?,
+        vec!["ship", "turret", "barrel", "thruster", "escort"]
+    );
+    assert_eq!(
+        names(world.descendants_breadth_first(fleet)?.collect())?,
+        vec!["ship", "escort", "turret", "thruster", "barrel"]
+    );
+
+    world.despawn_recursive(ship)?;
+
+    for part in [ship, turret, barrel, thruster] {
+        assert!(!world.is_alive(part));
+    }
+    assert_eq!(world.children(fleet)?, vec![escort]);
+    assert_eq!(world.parent(escort)?, Some(fleet));
+
+    let parents: Vec<Entity> = world
+        .query::<&Parent>()?
+        .iter()
+        .map(|(_entity, parent)| parent.get())
+        .collect();
+    assert_eq!(parents, vec![fleet]);
+    Ok(())
+}
+
+#[test]
+fn deleting_a_single_entity_keeps_the_hierarchy_consistent() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Name>();
+
+    let root = spawn(&mut world, "root")?;
+    let middle = spawn(&mut world, "middle")?;
+    let leaf = spawn(&mut world, "leaf")?;
+    world.add_child(root, middle)?;
+    world.add_child(middle, leaf)?;
+
+    world.delete_entity_by_id(middle)?;
+
+    assert!(world.children(root)?.is_empty());
+    assert!(world.is_alive(leaf));
+    assert_eq!(world.parent(leaf)?, None);
+    assert!(world.query::<&Children>()?.iter().next().is_none());
+    Ok(())
+}
+
+#[test]
+fn removing_hierarchy_components_directly_keeps_both_sides_in_step() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Name>();
+
+    let root = spawn(&mut world, "root")?;
+    let first = spawn(&mut world, "first")?;
+    let second = spawn(&mut world, "second")?;
+    world.add_child(root, first)?;
+    world.add_child(root, second)?;
+
+    world.delete_component_by_entity_id::<Parent>(first)?;
+    world.delete_entity_by_id(first)?;
+    assert_eq!(world.children(root)?, vec![second]);
+
+    world.delete_component_by_entity_id::<Children>(root)?;
+    assert_eq!(world.parent(second)?, None);
+
+    let third = spawn(&mut world, "third")?;
+    world.add_child(root, third)?;
+    world.despawn_recursive(root)?;
+    assert!(!world.is_alive(third));
+    assert!(world.is_alive(second));
+    Ok(())
+}
+
+fn spawn(world: &mut World, name: &'static str) -> Result<Entity> {
+    Ok(world.create_entity().with_component(Name(name))?.entity())
+}
+
+fn name_of(world: &World, entity: Entity) -> Result<&'static str> {
+    world
+        .query::<&Name>()?
+        .iter()
+        .find(|(found, _name)| *found == entity)
+        .map(|(_entity, name)| name.0)
+        .ok_or_else(|| eyre::eyre!("no name"))
+}
+
+struct Name(pub &'static str);
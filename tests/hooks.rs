@@ -0,0 +1,132 @@
+use std::sync::{Arc, Mutex};
+
+use ecs_in_rust::*;
+use eyre::Result;
+
+#[test]
+fn hooks_follow_the_component_lifetime() -> Result<()> {
+    let log = Arc::new(Mutex::new(vec![]));
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component_with_hooks(logging_hooks(&log));
+
+    let first = world.spawn((Location(0.0), Collider(1)))?;
+    world.add_component_to_entity_by_id(Collider(2), first)?;
+    world.delete_component_by_entity_id::<Collider>(first)?;
+    world.delete_component_by_entity_id::<Collider>(first)?;
+    world.insert_bundle(first, (Collider(3),))?;
+    world.delete_entity_by_id(first)?;
+
+    let second = world.create_entity().with_component(Collider(4))?.entity();
+    world.remove_bundle::<(Collider, Location)>(second)?;
+    world.spawn_batch([(Collider(5),)])?;
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            ("add", 1),
+            ("insert", 1),
+            ("insert", 2),
+            ("remove", 2),
+            ("add", 3),
+            ("insert", 3),
+            ("remove", 3),
+            ("add", 4),
+            ("insert", 4),
+            ("remove", 4),
+            ("add", 5),
+            ("insert", 5),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn hook_commands_are_deferred() -> Result<()> {
+    let mut world = World::new();
+    world.add_resource(Slots(vec![]));
+    world.register_component::<Location>();
+    world.register_component_with_hooks(
+        ComponentHooks::<Collider>::new()
+            .on_add(|collider, entity, commands| {
+                let slot = collider.0;
+                commands
+                    .insert(entity, Location(slot as f32))
+                    .add(entity, move |world| {
+                        world.get_resource_mut::<Slots>().unwrap().0.push(slot);
+                        Ok(())
+                    });
+            })
+            .on_remove(|collider, entity, commands| {
+                let slot = collider.0;
+                commands.add(entity, move |world| {
+                    world
+                        .get_resource_mut::<Slots>()
+                        .unwrap()
+                        .0
+                        .retain(|taken| *taken != slot);
+                    Ok(())
+                });
+            }),
+    );
+
+    let wall = world.spawn((Collider(7),))?;
+    assert_eq!(world.get_resource::<Slots>().unwrap().0, vec![7]);
+    let locations: Vec<(Entity, f32)> = world
+        .query::<&Location>()?
+        .iter()
+        .map(|(entity, location)| (entity, location.0))
+        .collect();
+    assert_eq!(locations, vec![(wall, 7.0)]);
+
+    world.create_entity().with_component(Collider(8))?;
+    assert_eq!(world.get_resource::<Slots>().unwrap().0, vec![7]);
+    world.flush()?;
+    assert_eq!(world.get_resource::<Slots>().unwrap().0, vec![7, 8]);
+
+    world.delete_entity_by_id(wall)?;
+    assert_eq!(world.get_resource::<Slots>().unwrap().0, vec![8]);
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(|world: &mut World| -> Result<()> {
+        world.create_entity().with_component(Collider(9))?;
+        Ok(())
+    })?;
+    schedule.run(&mut world)?;
+    assert_eq!(world.get_resource::<Slots>().unwrap().0, vec![8, 9]);
+    Ok(())
+}
+
+#[test]
+fn failing_hook_commands_are_reported() -> Result<()> {
+    let mut world = World::new();
+    world.register_component_with_hooks(ComponentHooks::<Collider>::new().on_remove(
+        |_collider, entity, commands| {
+            commands.despawn(entity);
+        },
+    ));
+
+    let wall = world.spawn((Collider(1),))?;
+    let error = world.delete_entity_by_id(wall).unwrap_err();
+    assert!(error.to_string().contains("despawn"));
+    assert!(!world.is_alive(wall));
+    Ok(())
+}
+
+fn logging_hooks(log: &Arc<Mutex<Vec<(&'static str, u32)>>>) -> ComponentHooks<Collider> {
+    let (add, insert, remove) = (log.clone(), log.clone(), log.clone());
+    ComponentHooks::new()
+        .on_add(move |collider: &Collider, _entity, _commands| {
+            add.lock().unwrap().push(("add", collider.0))
+        })
+        .on_insert(move |collider: &Collider, _entity, _commands| {
+            insert.lock().unwrap().push(("insert", collider.0))
+        })
+        .on_remove(move |collider: &Collider, _entity, _commands| {
+            remove.lock().unwrap().push(("remove", collider.0))
+        })
+}
+
+struct Location(pub f32);
+struct Collider(pub u32);
+struct Slots(pub Vec<u32>);
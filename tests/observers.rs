@@ -0,0 +1,155 @@
+use std::sync::{Arc, Mutex};
+
+use ecs_in_rust::*;
+use eyre::Result;
+
+#[test]
+fn observers_run_for_their_targets() -> Result<()> {
+    let log = Log::default();
+    let mut world = World::new();
+    let ship = world.create_entity().entity();
+    let station = world.create_entity().entity();
+
+    world.observe::<Damaged>(log.observer("global"));
+    world.observe_entity::<Damaged>(ship, log.observer("ship"))?;
+    world.observe::<Healed>(log.observer("healed"));
+
+    world.trigger(Damaged(3), [ship, station])?;
+    world.trigger(Damaged(1), [])?;
+    assert_eq!(
+        log.take(),
+        vec![
+            ("ship", Some(ship), 3),
+            ("global", Some(ship), 3),
+            ("global", Some(station), 3),
+            ("global", None, 1),
+        ]
+    );
+
+    world.delete_entity_by_id(ship)?;
+    assert!(world.trigger(Damaged(2), [ship]).is_err());
+    let reused = world.create_entity().entity();
+    world.trigger(Damaged(2), [reused])?;
+    assert_eq!(log.take(), vec![("global", Some(reused), 2)]);
+    Ok(())
+}
+
+#[test]
+fn propagating_events_climb_the_parents() -> Result<()> {
+    let log = Log::default();
+    let mut world = World::new();
+    let fleet = world.create_entity().entity();
+    let ship = world.create_entity().entity();
+    let turret = world.create_entity().entity();
+    world.add_child(fleet, ship)?;
+    world.add_child(ship, turret)?;
+
+    world.observe_entity::<Healed>(fleet, log.observer("fleet"))?;
+    world.observe_entity::<Healed>(ship, log.observer("ship"))?;
+    world.observe_entity::<Damaged>(fleet, log.observer("fleet"))?;
+    world.observe_entity::<Damaged>(ship, log.observer("ship"))?;
+
+    world.trigger(Healed(5), [turret])?;
+    assert_eq!(
+        log.take(),
+        vec![("ship", Some(ship), 5), ("fleet", Some(fleet), 5)]
+    );
+
+    world.trigger(Damaged(5), [turret])?;
+    assert!(log.take().is_empty());
+
+    world.observe_entity::<Healed>(ship, |trigger: &mut Trigger<'_, Healed>, _commands| {
+        trigger.propagate(trigger.event().0 > 10);
+    })?;
+    world.trigger(Healed(2), [turret])?;
+    world.trigger(Healed(20), [turret])?;
+    assert_eq!(
+        log.take(),
+        vec![
+            ("ship", Some(ship), 2),
+            ("ship", Some(ship), 20),
+            ("fleet", Some(fleet), 20),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn observers_queue_structural_changes() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Wreck>();
+    world.observe::<Damaged>(|trigger, commands| {
+        let target = trigger.target().unwrap();
+        if trigger.event().0 >= 10 {
+            let wreck = commands.spawn();
+            commands.insert(wreck, Wreck(target)).despawn(target);
+        }
+    });
+    world.observe::<Damaged>(|trigger, _commands| {
+        assert!(trigger.world().is_alive(trigger.target().unwrap()));
+    });
+
+    let ship = world.create_entity().entity();
+    world.trigger(Damaged(4), [ship])?;
+    assert!(world.is_alive(ship));
+
+    world.trigger(Damaged(10), [ship])?;
+    assert!(!world.is_alive(ship));
+    let wrecks: Vec<Entity> = world
+        .query::<&Wreck>()?
+        .iter()
+        .map(|(_entity, wreck)| wreck.0)
+        .collect();
+    assert_eq!(wrecks, vec![ship]);
+    Ok(())
+}
+
+struct Damaged(pub u32);
+impl EntityEvent for Damaged {}
+
+struct Healed(pub u32);
+impl EntityEvent for Healed {
+    const PROPAGATE: bool = true;
+}
+
+struct Wreck(pub Entity);
+
+type Entry = (&'static str, Option<Entity>, u32);
+
+#[derive(Default, Clone)]
+struct Log(Arc<Mutex<Vec<Entry>>>);
+
+impl Log {
+    fn observer<E: EntityEvent + Amount>(
+        &self,
+        name: &'static str,
+    ) -> impl FnMut(&mut Trigger<'_, E>, &mut Commands<'_, '_>) + Send + Sync + 'static {
+        let log = self.clone();
+        move |trigger, _commands| {
+            log.0
+                .lock()
+                .unwrap()
+                .push((name, trigger.entity(), trigger.event().amount()))
+        }
+    }
+
+    fn take(&self) -> Vec<Entry> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+trait Amount {
+    fn amount(&self) -> u32;
+}
+
+impl Amount for Damaged {
+    fn amount(&self) -> u32 {
+        self.0
+    }
+}
+
+impl Amount for Healed {
+    fn amount(&self) -> u32 {
+        self.0
+    }
+}
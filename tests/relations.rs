@@ -0,0 +1,111 @@
+use ecs_in_rust::*;
+use eyre::Result;
+
+#[test]
+fn reverse_index_answers_who_points_at_an_entity() -> Result<()> {
+    let mut world = World::new();
+    let enemy = world.create_entity().entity();
+    let other = world.create_entity().entity();
+    let first = world.create_entity().entity();
+    let second = world.create_entity().entity();
+
+    world.relate::<Targets>(first, enemy)?;
+    world.relate::<Targets>(second, enemy)?;
+    world.relate::<DockedAt>(first, other)?;
+    assert_eq!(world.sources::<Targets>(enemy), vec![first, second]);
+    assert_eq!(world.target::<DockedAt>(first)?, Some(other));
+
+    world.relate::<Targets>(first, other)?;
+    assert_eq!(world.sources::<Targets>(enemy), vec![second]);
+    assert_eq!(world.sources::<Targets>(other), vec![first]);
+
+    world.unrelate::<Targets>(second)?;
+    assert!(world.sources::<Targets>(enemy).is_empty());
+    assert_eq!(world.target::<Targets>(second)?, None);
+
+    let targeting: Vec<(Entity, Entity)> = world
+        .query::<&Related<Targets>>()?
+        .iter()
+        .map(|(source, related)| (source, related.target()))
+        .collect();
+    assert_eq!(targeting, vec![(first, other)]);
+    Ok(())
+}
+
+#[test]
+fn deleting_a_target_cleans_up_its_relations() -> Result<()> {
+    let mut world = World::new();
+    let station = world.create_entity().entity();
+    let faction = world.create_entity().entity();
+    let ship = world.create_entity().entity();
+    let member = world.create_entity().entity();
+
+    world.relate::<DockedAt>(ship, station)?;
+    world.relate::<Targets>(station, ship)?;
+    world.relate::<MemberOf>(member, faction)?;
+
+    let mut undocked = RemovedComponents::<Related<DockedAt>>::new();
+    world.delete_entity_by_id(station)?;
+
+    assert!(world.is_alive(ship));
+    assert_eq!(world.target::<DockedAt>(ship)?, None);
+    assert_eq!(undocked.read(&world), vec![ship]);
+    assert!(world.sources::<Targets>(ship).is_empty());
+
+    world.delete_entity_by_id(faction)?;
+    assert!(!world.is_alive(member));
+    assert!(world.sources::<MemberOf>(faction).is_empty());
+    Ok(())
+}
+
+#[test]
+fn removing_the_component_directly_updates_the_index() -> Result<()> {
+    let mut world = World::new();
+    let station = world.create_entity().entity();
+    let docked = world.create_entity().entity();
+    let undocked = world.create_entity().entity();
+    let hunter = world.create_entity().entity();
+    world.relate::<DockedAt>(docked, station)?;
+    world.relate::<DockedAt>(undocked, station)?;
+    world.relate::<Targets>(hunter, station)?;
+
+    world.delete_component_by_entity_id::<Related<DockedAt>>(undocked)?;
+    world.remove_bundle::<(Related<Targets>,)>(hunter)?;
+    assert_eq!(world.sources::<DockedAt>(station), vec![docked]);
+    assert!(world.sources::<Targets>(station).is_empty());
+
+    let mut removed = RemovedComponents::<Related<DockedAt>>::new();
+    world.delete_entity_by_id(station)?;
+    assert_eq!(removed.read(&world), vec![undocked, docked]);
+    assert_eq!(world.target::<DockedAt>(docked)?, None);
+    Ok(())
+}
+
+#[test]
+fn rollback_rebuilds_the_reverse_index() -> Result<()> {
+    let mut world = World::new();
+    let enemy = world.create_entity().entity();
+    let hunter = world.create_entity().entity();
+    world.relate::<Targets>(hunter, enemy)?;
+    world
+        .rollback_mut()
+        .register_component::<Related<Targets>>();
+
+    world.save_rollback(0)?;
+    world.unrelate::<Targets>(hunter)?;
+    world.rollback_to(0)?;
+
+    assert_eq!(world.sources::<Targets>(enemy), vec![hunter]);
+    Ok(())
+}
+
+struct Targets;
+impl Relation for Targets {}
+
+struct DockedAt;
+impl Relation for DockedAt {}
+
+struct MemberOf;
+impl Relation for MemberOf {
+    const ON_DELETE: OnDelete = OnDelete::Delete;
+}
@@ -0,0 +1,96 @@
+use ecs_in_rust::*;
+use eyre::Result;
+
+#[test]
+fn rolling_back_restores_entities_components_and_resources() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Velocity>();
+    world.add_resource(Frame(0));
+    world.rollback_mut().register_component::<Location>();
+    world.rollback_mut().register_component::<Velocity>();
+    world.rollback_mut().register_resource::<Frame>();
+    world.rollback_mut().set_capacity(3);
+
+    let ship = world
+        .create_entity()
+        .with_component(Location(0.0))?
+        .with_component(Velocity(1.0))?
+        .entity();
+    let rock = world
+        .create_entity()
+        .with_component(Location(5.0))?
+        .entity();
+
+    let mut history = vec![];
+    for tick in 0..5 {
+        world.save_rollback(tick)?;
+        history.push(state(&world)?);
+        simulate(&mut world)?;
+        if tick == 2 {
+            world.delete_entity_by_id(rock)?;
+            world.create_entity().with_component(Location(9.0))?;
+        }
+    }
+
+    assert_eq!(world.rollback().ticks(), vec![2, 3, 4]);
+    assert!(world.rollback_to(1).is_err());
+
+    world.rollback_to(2)?;
+    assert_eq!(state(&world)?, history[2]);
+    assert!(world.is_alive(rock));
+    assert_eq!(world.rollback().ticks(), vec![2]);
+
+    simulate(&mut world)?;
+    world.rollback_to(2)?;
+    assert_eq!(state(&world)?, history[2]);
+
+    world.delete_entity_by_id(ship)?;
+    let created = world
+        .create_entity()
+        .with_component(Location(1.0))?
+        .entity();
+    assert_eq!(created.index, ship.index);
+    assert_ne!(created, ship);
+    Ok(())
+}
+
+#[test]
+fn every_component_has_to_be_registered_for_rollback() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Velocity>();
+    world.rollback_mut().register_component::<Location>();
+
+    let error = world.save_rollback(0).unwrap_err();
+    assert!(error.to_string().contains("Velocity"));
+    Ok(())
+}
+
+type State = (u32, Vec<(Entity, f32, Option<f32>)>);
+
+fn state(world: &World) -> Result<State> {
+    let entities = world
+        .query::<(&Location, Option<&Velocity>)>()?
+        .iter()
+        .map(|(entity, location, velocity)| {
+            (entity, location.0, velocity.map(|velocity| velocity.0))
+        })
+        .collect();
+    Ok((world.get_resource::<Frame>().unwrap().0, entities))
+}
+
+fn simulate(world: &mut World) -> Result<()> {
+    for (_entity, mut location, velocity) in world.query::<(&mut Location, &Velocity)>()?.iter() {
+        location.0 += velocity.0;
+    }
+    world.get_resource_mut::<Frame>().unwrap().0 += 1;
+    Ok(())
+}
+
+#[derive(Clone)]
+struct Location(pub f32);
+#[derive(Clone)]
+struct Velocity(pub f32);
+#[derive(Clone)]
+struct Frame(pub u32);
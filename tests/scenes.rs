@@ -0,0 +1,133 @@
+use ecs_in_rust::*;
+use eyre::Result;
+
+#[test]
+fn scenes_spawn_registered_components() -> Result<()> {
+    let mut world = World::new();
+    let spawned = world.spawn_scene(
+        r#"
+        entity {
+            Location(1.0, 2)
+            Sprite { name: "ship", frames: [0, 1, 2], tint: None }
+        }
+
+        // not drawn
+        entity { Location(-3.5, 0.0), Dead }
+        "#,
+        &registry(),
+    )?;
+
+    assert_eq!(spawned.len(), 2);
+
+    let entities: Vec<_> = world
+        .query::<(&Location, Option<&Sprite>)>()?
+        .iter()
+        .map(|(entity, location, sprite)| {
+            (
+                entity,
+                location.0,
+                location.1,
+                sprite.map(|sprite| (sprite.name.clone(), sprite.frames.clone(), sprite.tint)),
+            )
+        })
+        .collect();
+    assert_eq!(
+        entities,
+        vec![
+            (
+                spawned[0],
+                1.0,
+                2.0,
+                Some(("ship".to_owned(), vec![0, 1, 2], None))
+            ),
+            (spawned[1], -3.5, 0.0, None),
+        ]
+    );
+
+    let dead: Vec<Entity> = world
+        .query::<&Dead>()?
+        .iter()
+        .map(|(entity, _dead)| entity)
+        .collect();
+    assert_eq!(dead, vec![spawned[1]]);
+    Ok(())
+}
+
+#[test]
+fn scene_errors_carry_line_and_column() -> Result<()> {
+    let mut world = World::new();
+    let registry = registry();
+
+    let unknown = world
+        .spawn_scene(
+            "entity {\n    Location(0, 0)\n    Velocity(1, 1)\n}",
+            &registry,
+        )
+        .unwrap_err();
+    assert_eq!(unknown.to_string(), "3:5: unknown component Velocity");
+
+    let wrong_type = world
+        .spawn_scene(
+            "entity {\n    Sprite { name: 4, frames: [], tint: None }\n}",
+            &registry,
+        )
+        .unwrap_err();
+    assert_eq!(
+        wrong_type.to_string(),
+        "2:20: expected a string, found an integer"
+    );
+
+    let missing_field = world
+        .spawn_scene(
+            "entity { Sprite { name: \"a\", tint: Some(1) } }",
+            &registry,
+        )
+        .unwrap_err();
+    assert_eq!(missing_field.to_string(), "1:17: missing field frames");
+
+    assert!(world.query::<()>()?.iter().next().is_none());
+    Ok(())
+}
+
+fn registry() -> SceneRegistry {
+    let mut registry = SceneRegistry::new();
+    registry
+        .register_component::<Location>("Location")
+        .register_component::<Sprite>("Sprite")
+        .register_component::<Dead>("Dead");
+    registry
+}
+
+struct Location(pub f32, pub f32);
+struct Sprite {
+    name: String,
+    frames: Vec<u32>,
+    tint: Option<u32>,
+}
+struct Dead;
+
+impl FromSceneValue for Location {
+    fn from_value(value: &Value) -> Result<Self> {
+        let elements = value.elements(2)?;
+        Ok(Location(elements[0].parse()?, elements[1].parse()?))
+    }
+}
+
+impl FromSceneValue for Sprite {
+    fn from_value(value: &Value) -> Result<Self> {
+        Ok(Sprite {
+            name: value.field("name")?.parse()?,
+            frames: value.field("frames")?.parse()?,
+            tint: value.field("tint")?.parse()?,
+        })
+    }
+}
+
+impl FromSceneValue for Dead {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value.kind {
+            ValueKind::Unit => Ok(Dead),
+            _ => Err(value.unexpected("no body")),
+        }
+    }
+}
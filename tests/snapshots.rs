@@ -0,0 +1,125 @@
+use ecs_in_rust::*;
+use eyre::Result;
+
+#[test]
+fn deleted_handles_stay_stale_after_loading() -> Result<()> {
+    let mut world = World::new();
+    world.create_entity();
+    let deleted = world.create_entity().entity();
+    world.delete_entity_by_id(deleted)?;
+
+    let registry = SerializerRegistry::new();
+    let mut loaded = World::load_snapshot(&world.save_snapshot(&registry)?, &registry)?;
+    let created = loaded.create_entity().entity();
+
+    assert_eq!(created.index, deleted.index);
+    assert_ne!(created, deleted);
+    assert!(!loaded.is_alive(deleted));
+    Ok(())
+}
+
+#[test]
+fn worlds_round_trip_through_snapshots() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Name>();
+    world.register_component::<Scratch>();
+    world.add_resource(Wave(3));
+    world.add_resource(Scratch);
+
+    let ship = world
+        .create_entity()
+        .with_component(Location(1.0, 2.0))?
+        .with_component(Name("ship".to_owned()))?
+        .entity();
+    let deleted = world.create_entity().with_component(Scratch)?.entity();
+    let rock = world
+        .create_entity()
+        .with_component(Location(-4.0, 0.5))?
+        .with_component(Scratch)?
+        .entity();
+    world.delete_entity_by_id(deleted)?;
+    let reused = world
+        .create_entity()
+        .with_component(Name("new".to_owned()))?
+        .entity();
+
+    let mut registry = SerializerRegistry::new();
+    registry
+        .register_component::<Location>("location")
+        .register_component::<Name>("name")
+        .register_resource::<Wave>("wave");
+
+    let bytes = world.save_snapshot(&registry)?;
+    let mut loaded = World::load_snapshot(&bytes, &registry)?;
+
+    let locations: Vec<_> = loaded
+        .query::<(&Location, Option<&Name>)>()?
+        .iter()
+        .map(|(entity, location, name)| {
+            (
+                entity,
+                location.0,
+                location.1,
+                name.map(|name| name.0.clone()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        locations,
+        vec![
+            (ship, 1.0, 2.0, Some("ship".to_owned())),
+            (rock, -4.0, 0.5, None),
+        ]
+    );
+    assert!(loaded.is_alive(reused));
+    assert!(!loaded.is_alive(deleted));
+    assert_eq!(loaded.get_resource::<Wave>(), Some(&Wave(3)));
+    assert!(loaded.get_resource::<Scratch>().is_none());
+    assert_eq!(loaded.save_snapshot(&registry)?, bytes);
+
+    let created = loaded
+        .create_entity()
+        .with_component(Location(0.0, 0.0))?
+        .entity();
+    assert!(![ship, rock, reused].contains(&created));
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+struct Location(pub f32, pub f32);
+struct Name(pub String);
+struct Scratch;
+#[derive(Debug, PartialEq)]
+struct Wave(pub u32);
+
+impl Binary for Location {
+    fn write(&self, out: &mut Vec<u8>) {
+        (self.0, self.1).write(out);
+    }
+
+    fn read(reader: &mut Reader<'_>) -> Result<Self> {
+        let (x, y) = reader.read()?;
+        Ok(Location(x, y))
+    }
+}
+
+impl Binary for Name {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.0.write(out);
+    }
+
+    fn read(reader: &mut Reader<'_>) -> Result<Self> {
+        Ok(Name(reader.read()?))
+    }
+}
+
+impl Binary for Wave {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.0.write(out);
+    }
+
+    fn read(reader: &mut Reader<'_>) -> Result<Self> {
+        Ok(Wave(reader.read()?))
+    }
+}
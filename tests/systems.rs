@@ -0,0 +1,461 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use ecs_in_rust::*;
+use eyre::Result;
+
+#[test]
+fn schedule_runs_registered_functions() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Velocity>();
+    world.add_resource(Frames(0));
+
+    world
+        .create_entity()
+        .with_component(Location(0.0))?
+        .with_component(Velocity(2.0))?;
+    world.create_entity().with_component(Location(5.0))?;
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(movement)?.add_system(count_frames)?;
+
+    schedule.run(&mut world)?;
+    schedule.run(&mut world)?;
+
+    let locations: Vec<f32> = world
+        .query::<&Location>()?
+        .iter()
+        .map(|(_entity, location)| location.0)
+        .collect();
+    assert_eq!(locations, vec![4.0, 5.0]);
+    assert_eq!(world.get_resource::<Frames>().unwrap().0, 2);
+    Ok(())
+}
+
+#[test]
+fn systems_receive_their_parameters() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Velocity>();
+    world.add_resource(Frames(0));
+
+    world
+        .create_entity()
+        .with_component(Location(0.0))?
+        .with_component(Velocity(1.0))?;
+
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(injected_movement)?
+        .add_system(count_moved)?;
+
+    schedule.run(&mut world)?;
+    assert_eq!(world.get_resource::<Frames>().unwrap().0, 1);
+
+    schedule.run(&mut world)?;
+    assert_eq!(world.get_resource::<Frames>().unwrap().0, 1);
+    Ok(())
+}
+
+#[test]
+fn only_written_components_count_as_changed() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.add_resource(Frames(0));
+    world.spawn_batch([(Location(0.0),), (Location(10.0),)])?;
+
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(nudge_first.label("nudge"))?
+        .add_system(count_moved.after("nudge"))?;
+
+    schedule.run(&mut world)?;
+    assert_eq!(world.get_resource::<Frames>().unwrap().0, 2);
+    schedule.run(&mut world)?;
+    assert_eq!(world.get_resource::<Frames>().unwrap().0, 3);
+    schedule.run(&mut world)?;
+    assert_eq!(world.get_resource::<Frames>().unwrap().0, 4);
+    Ok(())
+}
+
+#[test]
+fn changes_between_runs_reach_the_last_system() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.add_resource(Frames(0));
+    world.add_resource(Log::default());
+    let moved = world.spawn((Location(0.0),))?;
+
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(count_frames)?
+        .add_system(count_added_and_changed)?;
+    schedule.run(&mut world)?;
+    assert_eq!(world.get_resource::<Log>().unwrap().0, vec![1, 1]);
+
+    world.spawn((Location(5.0),))?;
+    for (entity, mut location) in world.query::<&mut Location>()?.iter() {
+        if entity == moved {
+            location.0 += 1.0;
+        }
+    }
+    schedule.run(&mut world)?;
+    assert_eq!(world.get_resource::<Log>().unwrap().0, vec![1, 1, 1, 2]);
+    Ok(())
+}
+
+#[test]
+fn schedule_runs_trim_removed_components() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    let doomed = world.spawn((Location(0.0),))?;
+
+    let mut every_frame = RemovedComponents::<Location>::new();
+    let mut late = RemovedComponents::<Location>::new();
+    let mut schedule = Schedule::new();
+
+    world.delete_entity_by_id(doomed)?;
+    schedule.run(&mut world)?;
+    assert_eq!(every_frame.read(&world), vec![doomed]);
+
+    schedule.run(&mut world)?;
+    schedule.run(&mut world)?;
+    assert!(every_frame.read(&world).is_empty());
+    assert!(late.read(&world).is_empty());
+    Ok(())
+}
+
+#[test]
+fn conflicting_system_parameters_are_rejected() {
+    let mut schedule = Schedule::new();
+    let error = schedule.add_system(conflicting).err().unwrap();
+    assert!(error.to_string().contains("Location"));
+}
+
+#[test]
+fn parallel_executor_matches_single_threaded() -> Result<()> {
+    let mut results = vec![];
+    for executor in [
+        ExecutorKind::SingleThreaded,
+        ExecutorKind::Parallel { threads: 4 },
+    ] {
+        let mut world = World::new();
+        world.register_component::<Location>();
+        world.register_component::<Velocity>();
+        world.add_resource(Frames(0));
+
+        for x in 0..4 {
+            world
+                .create_entity()
+                .with_component(Location(x as f32))?
+                .with_component(Velocity(1.0))?;
+        }
+
+        let mut schedule = Schedule::new();
+        schedule
+            .set_executor(executor)
+            .add_system(injected_movement)?
+            .add_system(count_moved)?
+            .add_system(movement)?;
+
+        schedule.run(&mut world)?;
+        schedule.run(&mut world)?;
+
+        let locations: Vec<f32> = world
+            .query::<&Location>()?
+            .iter()
+            .map(|(_entity, location)| location.0)
+            .collect();
+        results.push((locations, world.get_resource::<Frames>().unwrap().0));
+    }
+
+    assert_eq!(results[0], results[1]);
+    Ok(())
+}
+
+#[test]
+fn compatible_systems_run_at_the_same_time() -> Result<()> {
+    let mut world = World::new();
+    world.add_resource(Arrived(AtomicUsize::new(0)));
+    world.add_resource(Met(AtomicUsize::new(0)));
+
+    let mut schedule = Schedule::new();
+    schedule
+        .set_executor(ExecutorKind::Parallel { threads: 2 })
+        .add_system(rendezvous)?
+        .add_system(rendezvous)?;
+
+    schedule.run(&mut world)?;
+
+    assert_eq!(
+        world
+            .get_resource::<Met>()
+            .unwrap()
+            .0
+            .load(Ordering::SeqCst),
+        2
+    );
+    Ok(())
+}
+
+#[test]
+fn labels_order_systems_regardless_of_insertion() -> Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Velocity>();
+    world.add_resource(Frames(0));
+
+    world
+        .create_entity()
+        .with_component(Location(0.0))?
+        .with_component(Velocity(1.0))?;
+
+    for executor in [
+        ExecutorKind::SingleThreaded,
+        ExecutorKind::Parallel { threads: 2 },
+    ] {
+        let mut schedule = Schedule::new();
+        schedule
+            .set_executor(executor)
+            .add_system(record_location.label("record").after("movement"))?
+            .add_system(movement.label("movement"))?;
+
+        schedule.run(&mut world)?;
+    }
+
+    assert_eq!(world.get_resource::<Frames>().unwrap().0, 2);
+    Ok(())
+}
+
+#[test]
+fn ordering_cycles_are_reported() -> Result<()> {
+    let mut world = World::new();
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(count_frames.label("count").before("movement"))?
+        .add_system(movement.label("movement").before("count"))?;
+
+    let error = schedule.run(&mut world).unwrap_err();
+    assert!(error.to_string().contains("count_frames"));
+    assert!(error.to_string().contains("movement"));
+    Ok(())
+}
+
+#[test]
+fn run_conditions_skip_systems() -> Result<()> {
+    for executor in [
+        ExecutorKind::SingleThreaded,
+        ExecutorKind::Parallel { threads: 2 },
+    ] {
+        let mut world = World::new();
+        world.register_component::<Location>();
+        world.register_component::<Velocity>();
+        world.add_resource(Frames(0));
+        world.add_resource(GameState::Paused);
+
+        world
+            .create_entity()
+            .with_component(Location(0.0))?
+            .with_component(Velocity(1.0))?;
+
+        let mut schedule = Schedule::new();
+        schedule
+            .set_executor(executor)
+            .configure_set(SystemSet::new("gameplay").run_if(resource_equals(GameState::Running)))
+            .add_system(movement.in_set("gameplay"))?
+            .add_system(record_location.after("gameplay").run_if(every_n_ticks(2)))?;
+
+        schedule.run(&mut world)?;
+        assert_eq!(world.get_resource::<Frames>().unwrap().0, 0);
+
+        world.add_resource(GameState::Running);
+        schedule.run(&mut world)?;
+        assert_eq!(world.get_resource::<Frames>().unwrap().0, 0);
+
+        schedule.run(&mut world)?;
+        assert_eq!(world.get_resource::<Frames>().unwrap().0, 2);
+    }
+    Ok(())
+}
+
+#[test]
+fn commands_are_applied_after_the_system() -> Result<()> {
+    for executor in [
+        ExecutorKind::SingleThreaded,
+        ExecutorKind::Parallel { threads: 2 },
+    ] {
+        let mut world = World::new();
+        world.register_component::<Location>();
+        world.register_component::<Velocity>();
+        world.add_resource(Frames(0));
+
+        world.create_entity().with_component(Location(-1.0))?;
+        world.create_entity().with_component(Location(3.0))?;
+
+        let mut schedule = Schedule::new();
+        schedule
+            .set_executor(executor)
+            .add_system(despawn_out_of_bounds)?
+            .add_system(record_location)?;
+
+        schedule.run(&mut world)?;
+        assert_eq!(world.get_resource::<Frames>().unwrap().0, 3);
+
+        let locations: Vec<f32> = world
+            .query::<(&Location, &Velocity)>()?
+            .iter()
+            .map(|(_entity, location, _velocity)| location.0)
+            .collect();
+        assert_eq!(locations, vec![0.0]);
+    }
+    Ok(())
+}
+
+#[test]
+fn events_reach_every_reader_once() -> Result<()> {
+    for executor in [
+        ExecutorKind::SingleThreaded,
+        ExecutorKind::Parallel { threads: 2 },
+    ] {
+        let mut world = World::new();
+        world.add_event::<Collision>();
+        world.add_resource(Frames(0));
+        world.add_resource(Log::default());
+
+        let mut schedule = Schedule::new();
+        schedule
+            .set_executor(executor)
+            .add_system(count_collisions.after("collide"))?
+            .add_system(log_collisions.run_if(on_event::<Collision>()))?
+            .add_system(collide.label("collide"))?;
+
+        schedule.run(&mut world)?;
+        world.send_event(Collision(7))?;
+        schedule.run(&mut world)?;
+
+        assert_eq!(world.get_resource::<Frames>().unwrap().0, 3);
+        assert_eq!(world.get_resource::<Log>().unwrap().0, vec![1, 7]);
+
+        world.get_resource_mut::<Frames>().unwrap().0 = 100;
+        schedule.run(&mut world)?;
+        assert_eq!(world.get_resource::<Log>().unwrap().0, vec![1, 7, 2]);
+        world.update_events();
+        world.update_events();
+        assert!(world
+            .get_resource::<Events<Collision>>()
+            .unwrap()
+            .is_empty());
+    }
+    Ok(())
+}
+
+fn collide(mut collisions: EventWriter<Collision>, frames: Res<Frames>) {
+    if frames.0 < 100 {
+        collisions.send(Collision(frames.0 + 1));
+    }
+}
+
+fn count_collisions(mut frames: ResMut<Frames>, mut collisions: EventReader<Collision>) {
+    frames.0 += collisions.read().count() as u32;
+}
+
+fn log_collisions(mut log: ResMut<Log>, mut collisions: EventReader<Collision>) {
+    log.0.extend(collisions.read().map(|collision| collision.0));
+}
+
+fn despawn_out_of_bounds(mut commands: Commands, query: Query<&Location>) {
+    for (entity, location) in query.iter() {
+        if location.0 < 0.0 {
+            commands.despawn(entity);
+            let replacement = commands.spawn();
+            commands
+                .insert(replacement, Location(0.0))
+                .insert(replacement, Velocity(1.0));
+        }
+    }
+}
+
+fn record_location(mut frames: ResMut<Frames>, query: Query<&Location>) {
+    frames.0 = query
+        .iter()
+        .map(|(_entity, location)| location.0 as u32)
+        .sum();
+}
+
+fn rendezvous(arrived: Res<Arrived>, met: Res<Met>) {
+    arrived.0.fetch_add(1, Ordering::SeqCst);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if arrived.0.load(Ordering::SeqCst) == 2 {
+            met.0.fetch_add(1, Ordering::SeqCst);
+            return;
+        }
+        thread::yield_now();
+    }
+}
+
+/// Only moves on the first frame, but borrows every location mutably on each run.
+fn injected_movement(frames: Res<Frames>, query: Query<(&mut Location, &Velocity)>) {
+    for (_entity, mut location, velocity) in query.iter() {
+        if frames.0 == 0 {
+            location.0 += velocity.0;
+        }
+    }
+}
+
+fn count_moved(mut frames: ResMut<Frames>, mut query: Query<&Location>) -> Result<()> {
+    query.filter::<Changed<Location>>()?;
+    frames.0 += query.iter().count() as u32;
+    Ok(())
+}
+
+fn count_added_and_changed(
+    mut log: ResMut<Log>,
+    mut added: Query<&Location>,
+    mut changed: Query<&Location>,
+) -> Result<()> {
+    added.filter::<Added<Location>>()?;
+    changed.filter::<Changed<Location>>()?;
+    log.0.push(added.iter().count() as u32);
+    log.0.push(changed.iter().count() as u32);
+    Ok(())
+}
+
+fn nudge_first(query: Query<&mut Location>) {
+    for (_entity, mut location) in query.iter() {
+        if location.0 < 5.0 {
+            location.0 += 1.0;
+        }
+    }
+}
+
+fn conflicting(_locations: Query<&mut Location>, _others: Query<(&Velocity, &Location)>) {}
+
+fn movement(world: &mut World) -> Result<()> {
+    for (_entity, mut location, velocity) in world.query::<(&mut Location, &Velocity)>()?.iter() {
+        location.0 += velocity.0;
+    }
+    Ok(())
+}
+
+fn count_frames(world: &mut World) {
+    world.get_resource_mut::<Frames>().unwrap().0 += 1;
+}
+
+struct Location(pub f32);
+struct Velocity(pub f32);
+struct Frames(pub u32);
+struct Collision(pub u32);
+#[derive(Default)]
+struct Log(pub Vec<u32>);
+#[derive(PartialEq)]
+enum GameState {
+    Paused,
+    Running,
+}
+struct Arrived(pub AtomicUsize);
+struct Met(pub AtomicUsize);